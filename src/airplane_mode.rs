@@ -0,0 +1,208 @@
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    network::{enums::RadioFlags, network_manager::NetworkManager},
+    rfkill::{self, RadioType},
+};
+
+const STATE_DIR: &str = "networkless";
+const STATE_FILE: &str = "airplane-mode.toml";
+
+/// The radios as they were before airplane mode was turned on. Airplane mode is on for as long
+/// as a snapshot is saved.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct Snapshot {
+    /// Left out when the machine has no radio of that kind.
+    wifi: Option<bool>,
+    wwan: Option<bool>,
+    bluetooth: Vec<RadioSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RadioSnapshot {
+    index: u32,
+    blocked: bool,
+}
+
+pub fn is_enabled() -> bool {
+    match load() {
+        Ok(snapshot) => snapshot.is_some(),
+        Err(e) => {
+            warn!("{:#}", e);
+            false
+        }
+    }
+}
+
+/// Saves the state of every radio and turns them all off.
+pub async fn enable(network_manager: &NetworkManager) -> Result<()> {
+    if load()?.is_some() {
+        info!("Airplane mode is already on");
+        return Ok(());
+    }
+
+    let radio_flags = network_manager.radio_flags().await?;
+
+    let wifi = match radio_flags.contains(RadioFlags::WLAN_AVAILABLE) {
+        true => Some(network_manager.wifi_enabled().await?),
+        false => None,
+    };
+    let wwan = match radio_flags.contains(RadioFlags::WWAN_AVAILABLE) {
+        true => Some(network_manager.wwan_enabled().await?),
+        false => None,
+    };
+    let bluetooth = rfkill::radios_of_type(RadioType::Bluetooth)?
+        .into_iter()
+        .map(|radio| RadioSnapshot {
+            index: radio.index,
+            blocked: radio.soft_blocked,
+        })
+        .collect::<Vec<RadioSnapshot>>();
+
+    let snapshot = Snapshot {
+        wifi,
+        wwan,
+        bluetooth,
+    };
+
+    info!("Turning airplane mode on from {:?}", snapshot);
+
+    // Saved first so that the radios can be restored even if blocking one of them fails.
+    save(&snapshot)?;
+
+    if snapshot.wifi.is_some() {
+        network_manager.set_wifi_enabled(false).await?;
+    }
+    if snapshot.wwan.is_some() {
+        network_manager.set_wwan_enabled(false).await?;
+    }
+    if !snapshot.bluetooth.is_empty() {
+        rfkill::set_blocked(RadioType::Bluetooth, true)?;
+    }
+
+    Ok(())
+}
+
+/// Turns the radios back to the way they were when airplane mode was turned on.
+pub async fn disable(network_manager: &NetworkManager) -> Result<()> {
+    let Some(snapshot) = load()? else {
+        info!("Airplane mode is already off");
+        return Ok(());
+    };
+
+    info!("Turning airplane mode off to {:?}", snapshot);
+
+    if let Some(wifi) = snapshot.wifi {
+        network_manager.set_wifi_enabled(wifi).await?;
+    }
+    if let Some(wwan) = snapshot.wwan {
+        network_manager.set_wwan_enabled(wwan).await?;
+    }
+
+    // Adapters may have come and gone in the meantime, so only the ones still around are
+    // restored.
+    let radios = rfkill::radios_of_type(RadioType::Bluetooth)?;
+    for radio_snapshot in &snapshot.bluetooth {
+        if radios
+            .iter()
+            .any(|radio| radio.index == radio_snapshot.index)
+        {
+            rfkill::set_radio_blocked(radio_snapshot.index, radio_snapshot.blocked)?;
+        }
+    }
+
+    remove()
+}
+
+/// Records Wi-Fi being turned on or off while airplane mode is on, so that turning airplane mode
+/// off keeps that choice rather than restoring Wi-Fi to how it was before. Does nothing while
+/// airplane mode is off.
+pub fn remember_wifi(on: bool) -> Result<()> {
+    update(|snapshot| snapshot.wifi = Some(on))
+}
+
+/// Like [`remember_wifi`], for mobile broadband.
+pub fn remember_wwan(on: bool) -> Result<()> {
+    update(|snapshot| snapshot.wwan = Some(on))
+}
+
+/// Like [`remember_wifi`], for every Bluetooth adapter.
+pub fn remember_bluetooth(on: bool) -> Result<()> {
+    update(|snapshot| {
+        for radio in &mut snapshot.bluetooth {
+            radio.blocked = !on;
+        }
+    })
+}
+
+fn update(f: impl FnOnce(&mut Snapshot)) -> Result<()> {
+    let Some(mut snapshot) = load()? else {
+        return Ok(());
+    };
+
+    f(&mut snapshot);
+
+    info!("Airplane mode will restore {:?}", snapshot);
+
+    save(&snapshot)
+}
+
+fn state_home() -> Option<PathBuf> {
+    match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")),
+    }
+}
+
+fn state_file() -> Result<PathBuf> {
+    match state_home() {
+        Some(dir) => Ok(dir.join(STATE_DIR).join(STATE_FILE)),
+        None => bail!("Neither XDG_STATE_HOME nor HOME is set"),
+    }
+}
+
+fn load() -> Result<Option<Snapshot>> {
+    let path = state_file()?;
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+    };
+
+    let snapshot =
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(Some(snapshot))
+}
+
+fn save(snapshot: &Snapshot) -> Result<()> {
+    let path = state_file()?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    fs::write(&path, toml::to_string(snapshot)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn remove() -> Result<()> {
+    let path = state_file()?;
+
+    match fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => bail!("Failed to remove {}: {}", path.display(), e),
+    }
+}
@@ -2,53 +2,105 @@ use std::{
     cmp::Ordering,
     collections::{HashMap, HashSet},
     ops::ControlFlow,
-    time::Duration,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
 };
 
 use anyhow::Result;
-use futures::StreamExt;
 use log::{error, info, warn};
 use tokio::{
     sync::mpsc::{Receiver, Sender},
     task::JoinHandle,
+    time::MissedTickBehavior,
+};
+use zbus::{
+    Connection,
+    zvariant::{ObjectPath, OwnedObjectPath},
 };
-use zbus::zvariant::{ObjectPath, OwnedObjectPath};
 
 use crate::{
+    airplane_mode,
+    config::{Config, SortKey, listening_to_config_changes},
+    launcher,
     network::{
+        active_connection::ActiveConnection,
         device::Device,
         devices::SpecificDevice,
-        enums::{ActiveConnectionState, DeviceState, DeviceType, NmConnectivityState, NmState},
+        enums::{
+            ActiveConnectionState, DeviceState, DeviceType, NmConnectivityState, NmState,
+            RadioFlags,
+        },
         network_manager::NetworkManager,
+        properties,
     },
+    notifications::{Category, Notifier},
+    rfkill::{self, RadioType},
+    scheduler::{Subsystems, UpdateScheduler},
+    sleep,
+    systemd::{self, Watchdog},
     trays::{
-        AirplaneModeState, Icon, TrayManager, TrayUpdate, VPNConnection, VPNState, WifiConnection,
-        WifiState, WiredState,
+        ActivationStage, AirplaneModeState, Badges, Base, Connectivity, ConnectivityState, Icon,
+        NetworkingState, PrimaryConnectionState, RadioKind, RadioState, TrayManager, TrayUpdate,
+        VPNConnection, VPNState, WifiActivation, WifiConnection, WifiState, WiredState,
+        clear_icon_cache,
     },
 };
 
+/// Opened to reach the captive portal when NetworkManager has no connectivity check configured.
+/// It is served over plain HTTP so that portals can redirect it to their login page.
+const FALLBACK_PORTAL_URI: &str = "http://neverssl.com/";
+/// How often and for how long to check whether the user has signed in to a captive portal.
+const PORTAL_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const PORTAL_CHECK_TIMEOUT: Duration = Duration::from_secs(600);
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Event {
     Init,
-    Update,
+    Update(Subsystems),
+    /// A device was added or removed.
+    DevicesChanged,
+    /// Another connection became the primary one.
+    PrimaryConnectionChanged,
+    NetworkManagerStarted,
+    NetworkManagerStopped,
+    Resumed,
+    SystemBusDisconnected,
+    StatusNotifierHostRegistered,
+    ConfigChanged,
     Shutdown,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Action {
     ChangeAccessPoint(WifiConnection),
+    /// Connects to the strongest access point with this SSID.
+    ConnectTo(String),
     ToggleWifi,
     ToggleWired,
+    ToggleBluetooth,
+    ToggleWwan,
     ToggleAirplaneMode,
+    ToggleNetworking,
     ToggleVPN(String),
     RequestScan,
+    OpenCaptivePortal,
+    CheckConnectivity,
+    ToggleConnectivityCheck,
 }
 
 #[derive(Debug, Clone)]
 pub struct App {
     event_tx: Sender<Event>,
     action_tx: Sender<Action>,
-    network_manager: NetworkManager,
+    network_manager: Arc<RwLock<NetworkManager>>,
+    config: Arc<RwLock<Config>>,
+    /// When each VPN connection, by id, was first seen activated.
+    vpn_connected_since: Arc<RwLock<HashMap<String, SystemTime>>>,
+    /// State and connectivity seen on the previous update, to notify about changes.
+    last_state: Arc<RwLock<Option<NmState>>>,
+    last_connectivity: Arc<RwLock<Option<NmConnectivityState>>>,
+    notifier: Notifier,
+    portal_check_handle: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 impl App {
@@ -56,18 +108,62 @@ impl App {
         event_tx: Sender<Event>,
         action_tx: Sender<Action>,
         network_manager: NetworkManager,
+        config: Config,
     ) -> Self {
         Self {
             event_tx,
             action_tx,
-            network_manager,
+            network_manager: Arc::new(RwLock::new(network_manager)),
+            config: Arc::new(RwLock::new(config)),
+            vpn_connected_since: Default::default(),
+            last_state: Default::default(),
+            last_connectivity: Default::default(),
+            notifier: Notifier::default(),
+            portal_check_handle: Default::default(),
+        }
+    }
+
+    fn network_manager(&self) -> NetworkManager {
+        self.network_manager.read().unwrap().clone()
+    }
+
+    fn set_network_manager(&self, network_manager: NetworkManager) {
+        *self.network_manager.write().unwrap() = network_manager;
+    }
+
+    pub fn config(&self) -> Config {
+        self.config.read().unwrap().clone()
+    }
+
+    fn set_config(&self, config: Config) {
+        *self.config.write().unwrap() = config;
+    }
+
+    /// Shows a desktop notification unless its category is turned off in the config.
+    fn notify(&self, category: Category, summary: impl Into<String>, body: impl Into<String>) {
+        if !self.config().notifications.is_enabled(category) {
+            return;
         }
+
+        let notifier = self.notifier.clone();
+        let summary = summary.into();
+        let body = body.into();
+        tokio::spawn(async move {
+            if let Err(e) = notifier.notify(category, &summary, &body).await {
+                warn!("Failed to show notification: {}", e);
+            }
+        });
     }
 
     pub async fn send_event(&self, event: Event) {
         self.event_tx.send(event).await.unwrap();
     }
 
+    pub async fn send_action(&self, action: Action) -> Result<()> {
+        self.action_tx.send(action).await?;
+        Ok(())
+    }
+
     pub fn send_action_blocking(&self, action: Action) {
         let handle = tokio::runtime::Handle::current();
 
@@ -79,43 +175,57 @@ impl App {
         });
     }
 
-    pub async fn toggle_wifi(&self) {
-        let on = self.network_manager.wifi_enabled().await.unwrap();
+    pub async fn toggle_wifi(&self) -> Result<()> {
+        let on = self.network_manager().wifi_enabled().await?;
 
-        match self.network_manager.set_wifi_enabled(!on).await {
-            Ok(_) => {}
+        match self.network_manager().set_wifi_enabled(!on).await {
+            Ok(_) => {
+                if let Err(e) = airplane_mode::remember_wifi(!on) {
+                    error!("Failed to update airplane mode: {:#}", e);
+                }
+            }
             Err(e) => {
                 error!("Failed to set wifi enabled: {}", e);
             }
         }
 
-        self.send_event(Event::Update).await;
+        self.send_event(Event::Update(Subsystems::all())).await;
+
+        Ok(())
     }
 
-    pub async fn toggle_wired(&self) {
-        let devices = self.network_manager.devices().await.unwrap();
+    pub async fn toggle_wired(&self) -> Result<()> {
+        let devices = self.network_manager().devices().await?;
 
         for device in devices {
-            if device.device_type().await.unwrap() != DeviceType::Ethernet {
+            if device.device_type()? != DeviceType::Ethernet {
                 continue;
             }
 
-            let on = matches!(device.state().await.unwrap(), DeviceState::Activated);
+            let on = matches!(device.state()?, DeviceState::Activated);
 
             if on {
-                self.deactivate_wired_connection(&device).await;
+                self.deactivate_wired_connection(&device).await?;
             } else {
-                self.activate_wired_connection(device).await;
+                self.activate_wired_connection(device).await?;
             }
         }
+
+        Ok(())
     }
 
-    async fn deactivate_wired_connection(&self, device: &Device) {
-        let active_connection = device.active_connection().await.unwrap();
+    async fn deactivate_wired_connection(&self, device: &Device) -> Result<()> {
+        let active_connection = match device.active_connection().await? {
+            Some(active_connection) => active_connection,
+            None => {
+                warn!("Ethernet device has no active connection");
+                return Ok(());
+            }
+        };
         let connection_path = OwnedObjectPath::from(active_connection.path());
 
         match self
-            .network_manager
+            .network_manager()
             .deactivate_connection(connection_path)
             .await
         {
@@ -126,19 +236,21 @@ impl App {
                 error!("Failed to deactivate Ethernet device: {}", e);
             }
         };
+
+        Ok(())
     }
 
-    async fn activate_wired_connection(&self, device: Device) {
-        let available_connections = device.available_connections().await.unwrap();
+    async fn activate_wired_connection(&self, device: Device) -> Result<()> {
+        let available_connections = device.available_connections().await?;
         if available_connections.is_empty() {
-            return;
+            return Ok(());
         }
 
         for connection in available_connections {
             let device_path = device.path();
             let connection_path = OwnedObjectPath::from(connection.path());
             let activation_result = self
-                .network_manager
+                .network_manager()
                 .activate_connection(
                     connection_path,
                     OwnedObjectPath::from(device_path.clone()),
@@ -155,65 +267,107 @@ impl App {
                 }
             };
         }
+
+        Ok(())
+    }
+
+    pub async fn toggle_bluetooth(&self) -> Result<()> {
+        let on = rfkill::radios_of_type(RadioType::Bluetooth)?
+            .iter()
+            .any(|radio| !radio.soft_blocked);
+        rfkill::set_blocked(RadioType::Bluetooth, on)?;
+        // The radio has already changed, so a failure to remember it shouldn't fail the toggle.
+        if let Err(e) = airplane_mode::remember_bluetooth(!on) {
+            error!("Failed to update airplane mode: {:#}", e);
+        }
+
+        // The rfkill listener picks up the change as well, but may not be running.
+        self.send_event(Event::Update(Subsystems::AIRPLANE_MODE))
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn toggle_wwan(&self) -> Result<()> {
+        let on = self.network_manager().wwan_enabled().await?;
+        self.network_manager().set_wwan_enabled(!on).await?;
+        if let Err(e) = airplane_mode::remember_wwan(!on) {
+            error!("Failed to update airplane mode: {:#}", e);
+        }
+
+        self.send_event(Event::Update(Subsystems::all())).await;
+
+        Ok(())
     }
 
     pub async fn toggle_airplane_mode(&self) {
-        let on = self.network_manager.airplane_mode_enabled().await.unwrap();
+        let network_manager = self.network_manager();
 
-        match self.network_manager.set_airplane_mode_enabled(!on).await {
-            Ok(_) => {}
-            Err(e) => {
-                error!("Failed to set airplane mode: {}", e);
-            }
+        let result = match airplane_mode::is_enabled() {
+            true => airplane_mode::disable(&network_manager).await,
+            false => airplane_mode::enable(&network_manager).await,
         };
 
-        self.send_event(Event::Update).await;
+        if let Err(e) = result {
+            error!("Failed to set airplane mode: {:#}", e);
+        }
+
+        self.send_event(Event::Update(Subsystems::all())).await;
+    }
+
+    pub async fn toggle_networking(&self) -> Result<()> {
+        let on = self.network_manager().networking_enabled().await?;
+        self.network_manager().set_networking_enabled(!on).await?;
+
+        self.send_event(Event::Update(Subsystems::all())).await;
+
+        Ok(())
     }
 
-    pub async fn toggle_vpn(&self, vpn: String) {
-        let active_connections = self.network_manager.active_connections().await.unwrap();
+    pub async fn toggle_vpn(&self, vpn: String) -> Result<()> {
+        let active_connections = self.network_manager().active_connections().await?;
         for active_connection in active_connections {
-            if active_connection.device_type().await.unwrap() != DeviceType::WireGuard {
+            if active_connection.device_type()? != DeviceType::WireGuard {
                 continue;
             }
 
-            let vpn_name = active_connection.id().await.unwrap();
+            let vpn_name = active_connection.id()?;
 
             if vpn_name != vpn {
                 continue;
             }
 
-            let on = matches!(
-                active_connection.state().await.unwrap(),
-                ActiveConnectionState::Activated
-            );
+            let on = matches!(active_connection.state()?, ActiveConnectionState::Activated);
 
             if on {
                 let active_connection_path = OwnedObjectPath::from(active_connection.path());
-                self.network_manager
+                self.network_manager()
                     .deactivate_connection(active_connection_path)
-                    .await
-                    .unwrap();
+                    .await?;
             } else {
-                let specific_object_path = active_connection.specific_object().await.unwrap();
-                self.network_manager
+                let specific_object_path =
+                    active_connection.specific_object()?.unwrap_or_else(|| {
+                        OwnedObjectPath::from(ObjectPath::from_string_unchecked("/".into()))
+                    });
+                self.network_manager()
                     .activate_connection(
                         OwnedObjectPath::from(ObjectPath::from_string_unchecked("/".into())),
                         OwnedObjectPath::from(ObjectPath::from_string_unchecked("/".into())),
                         specific_object_path,
                     )
-                    .await
-                    .unwrap();
+                    .await?;
             }
         }
+
+        Ok(())
     }
 
     pub async fn change_access_point(&self, access_point: WifiConnection) -> Result<()> {
         let mut device = None;
 
-        for d in self.network_manager.all_devices().await.unwrap() {
-            let device_type = d.device_type().await.unwrap();
-            let state = d.state().await.unwrap();
+        for d in self.network_manager().devices().await? {
+            let device_type = d.device_type()?;
+            let state = d.state()?;
             if device_type == DeviceType::Wifi && state == DeviceState::Activated {
                 device = Some(d);
                 break;
@@ -237,15 +391,15 @@ impl App {
         let mut is_associated_active_connection_already_active = false;
 
         for c in self
-            .network_manager
+            .network_manager()
             .active_connections()
             .await
             .unwrap_or_default()
         {
             for ap in &mut access_points {
-                if ap.id().await.unwrap() == c.id().await.unwrap()
-                    && ap.hw_address().await.unwrap_or_default() == access_point.hw_address
-                    && c.state().await.unwrap() == ActiveConnectionState::Activated
+                if ap.id()? == c.id()?
+                    && ap.hw_address().unwrap_or_default() == access_point.hw_address
+                    && c.state()? == ActiveConnectionState::Activated
                 {
                     is_associated_active_connection_already_active = true;
                     break;
@@ -264,9 +418,7 @@ impl App {
 
         let mut found_access_point = None;
         for ap in &mut access_points {
-            if ap.id().await.unwrap() == access_point.ssid
-                && ap.hw_address().await.unwrap() == access_point.hw_address
-            {
+            if ap.id()? == access_point.ssid && ap.hw_address()? == access_point.hw_address {
                 found_access_point = Some(ap);
                 break;
             }
@@ -284,7 +436,7 @@ impl App {
         let mut configured_connection = None;
 
         for conf in &configured_connections {
-            if conf.id().await.unwrap() == found_access_point.id().await.unwrap() {
+            if conf.id().await? == found_access_point.id()? {
                 configured_connection = Some(conf);
                 break;
             }
@@ -308,19 +460,60 @@ impl App {
         info!("device_path {:?}", device_path);
         info!("access_point_path {:?}", access_point_path);
 
-        self.network_manager
+        self.network_manager()
             .activate_connection(configured_connection_path, device_path, access_point_path)
             .await?;
 
         Ok(())
     }
 
+    pub async fn connect_to(&self, ssid: &str) -> Result<()> {
+        let mut best = None;
+
+        for device in self.network_manager().devices().await? {
+            if device.device_type()? != DeviceType::Wifi {
+                continue;
+            }
+
+            let Some(SpecificDevice::Wireless(wireless_device)) = device.to_specific_device().await
+            else {
+                continue;
+            };
+
+            for access_point in wireless_device.access_points().await.unwrap_or_default() {
+                if access_point.id()? != ssid {
+                    continue;
+                }
+
+                let strength = access_point.strength().unwrap_or_default();
+                if best
+                    .as_ref()
+                    .is_some_and(|best: &WifiConnection| best.strength >= strength)
+                {
+                    continue;
+                }
+
+                best = Some(WifiConnection {
+                    ssid: ssid.to_string(),
+                    hw_address: access_point.hw_address()?,
+                    strength,
+                    secure: access_point.secure().unwrap_or_default(),
+                });
+            }
+        }
+
+        match best {
+            Some(access_point) => self.change_access_point(access_point).await,
+            None => anyhow::bail!("No access point named {} found", ssid),
+        }
+    }
+
     pub async fn request_scan(&self) -> Result<()> {
         let mut device = None;
 
-        for d in self.network_manager.all_devices().await.unwrap() {
-            let device_type = d.device_type().await.unwrap();
-            let state = d.state().await.unwrap();
+        for d in self.network_manager().devices().await? {
+            let device_type = d.device_type()?;
+            let state = d.state()?;
             if device_type == DeviceType::Wifi && state == DeviceState::Activated {
                 device = Some(d);
                 break;
@@ -342,173 +535,740 @@ impl App {
         Ok(())
     }
 
+    pub async fn check_connectivity(&self) -> Result<()> {
+        let connectivity = self.network_manager().check_connectivity().await?;
+        info!("Checked connectivity: {:?}", connectivity);
+
+        self.send_event(Event::Update(Subsystems::ICON)).await;
+
+        Ok(())
+    }
+
+    pub async fn toggle_connectivity_check(&self) -> Result<()> {
+        let network_manager = self.network_manager();
+        let enabled = network_manager.connectivity_check_enabled().await?;
+        network_manager
+            .set_connectivity_check_enabled(!enabled)
+            .await?;
+
+        self.send_event(Event::Update(Subsystems::ICON)).await;
+
+        Ok(())
+    }
+
+    /// Opens the captive portal's login page by requesting NetworkManager's connectivity check URI,
+    /// which the portal redirects, and checks connectivity again until the user has signed in.
+    pub async fn open_captive_portal(&self) -> Result<()> {
+        let uri = match self.network_manager().connectivity_check_uri().await? {
+            uri if uri.is_empty() => FALLBACK_PORTAL_URI.into(),
+            uri => uri,
+        };
+
+        launcher::open_uri(&uri).await?;
+
+        let app = self.clone();
+        let handle = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PORTAL_CHECK_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            let checking = async {
+                loop {
+                    interval.tick().await;
+
+                    match app.network_manager().check_connectivity().await {
+                        Ok(NmConnectivityState::Portal) => {}
+                        Ok(connectivity) => {
+                            info!("Signed in to captive portal: {:?}", connectivity);
+                            app.send_event(Event::Update(Subsystems::ICON)).await;
+                            return;
+                        }
+                        Err(e) => {
+                            error!("Failed to check connectivity: {}", e);
+                            return;
+                        }
+                    }
+                }
+            };
+
+            if tokio::time::timeout(PORTAL_CHECK_TIMEOUT, checking)
+                .await
+                .is_err()
+            {
+                warn!("Gave up waiting for captive portal sign-in");
+            }
+        });
+
+        if let Some(previous) = self.portal_check_handle.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+
+        Ok(())
+    }
+
     async fn setup_primary_connection_listener(&self) -> JoinHandle<()> {
         let app = self.clone();
         tokio::spawn(async move {
-            let primary_connection = app.network_manager.primary_connection().await.unwrap();
-            primary_connection
-                .listening_to_state_changes(async |_| {
-                    app.send_event(Event::Update).await;
+            let primary_connection = match app.network_manager().primary_connection().await {
+                Ok(Some(primary_connection)) => primary_connection,
+                Ok(None) => return,
+                Err(e) => {
+                    error!("Failed to get primary connection: {}", e);
+                    return;
+                }
+            };
+
+            let id = primary_connection.id().unwrap_or_default();
+
+            if let Err(e) = primary_connection
+                .listening_to_state_changes(async |state, reason| {
+                    if state == ActiveConnectionState::Deactivated && reason.is_failure() {
+                        app.notify(
+                            Category::ActivationFailed,
+                            format!("{} disconnected", id),
+                            reason.description(),
+                        );
+                    }
+
+                    app.send_event(Event::Update(Subsystems::ICON | Subsystems::WIFI))
+                        .await;
+                })
+                .await
+            {
+                error!(
+                    "Failed to listen to primary connection state changes: {}",
+                    e
+                );
+            }
+        })
+    }
+
+    async fn setup_device_state_listener(&self) -> JoinHandle<()> {
+        let app = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
+                .listening_to_device_state_changes(async |state, reason| {
+                    if state == DeviceState::Failed {
+                        app.notify(
+                            Category::ActivationFailed,
+                            "Connection failed",
+                            reason.description(),
+                        );
+                    }
+
+                    app.send_event(Event::Update(
+                        Subsystems::ICON | Subsystems::WIFI | Subsystems::WIRED | Subsystems::VPN,
+                    ))
+                    .await;
                 })
                 .await
-                .unwrap();
+            {
+                error!("Failed to listen to device state changes: {}", e);
+            }
         })
     }
 
+    /// Listens for access points coming and going on every Wi-Fi device, whatever its state.
     async fn setup_access_points_listener(&self) -> JoinHandle<()> {
         let app = self.clone();
         tokio::spawn(async move {
-            let mut device = None;
+            let devices = match app.network_manager().devices().await {
+                Ok(devices) => devices,
+                Err(e) => {
+                    error!("Failed to get devices: {}", e);
+                    return;
+                }
+            };
 
-            for d in app.network_manager.all_devices().await.unwrap() {
-                let device_type = d.device_type().await.unwrap();
-                let state = d.state().await.unwrap();
-                if device_type == DeviceType::Wifi && state == DeviceState::Activated {
-                    device = Some(d);
-                    break;
+            let mut wireless_devices = Vec::new();
+            for device in devices {
+                if !matches!(device.device_type(), Ok(DeviceType::Wifi)) {
+                    continue;
+                }
+
+                if let Some(SpecificDevice::Wireless(wireless_device)) =
+                    device.to_specific_device().await
+                {
+                    wireless_devices.push(wireless_device);
                 }
             }
 
-            let wireless_device = match device {
-                Some(device) => match device.to_specific_device().await {
-                    Some(SpecificDevice::Wireless(device)) => device,
-                    _ => {
-                        error!("Could not downcast device to Wireless device");
-                        return;
-                    }
-                },
-                None => {
-                    error!("Could not find an active Wifi device");
-                    return;
+            let listeners = wireless_devices.iter().map(|wireless_device| async {
+                let (added, removed) = tokio::join!(
+                    wireless_device.listening_to_access_point_added(async |_access_point| {
+                        app.send_event(Event::Update(Subsystems::WIFI)).await;
+                    }),
+                    wireless_device.listening_to_access_point_removed(async |_access_point| {
+                        app.send_event(Event::Update(Subsystems::WIFI)).await;
+                    }),
+                );
+
+                if let Err(e) = added {
+                    error!("Failed to listen to added access points: {}", e);
                 }
-            };
+                if let Err(e) = removed {
+                    error!("Failed to listen to removed access points: {}", e);
+                }
+            });
+
+            futures::future::join_all(listeners).await;
+        })
+    }
+
+    async fn setup_network_manager_listeners(&self) -> Vec<JoinHandle<()>> {
+        let mut handles = Vec::with_capacity(8);
 
-            wireless_device
-                .listening_to_access_point_added(async |_access_point| {
-                    info!("Access Point added");
-                    app.send_event(Event::Update).await;
+        let app = self.clone();
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
+                .listening_to_radio_changes(async || {
+                    app.send_event(Event::Update(
+                        Subsystems::AIRPLANE_MODE | Subsystems::WIFI | Subsystems::ICON,
+                    ))
+                    .await;
                 })
                 .await
-                .unwrap();
+            {
+                error!("Failed to listen to radio changes: {}", e);
+            }
+        }));
 
-            wireless_device
-                .listening_to_access_point_removed(async |_access_point| {
-                    info!("Access Point removed");
-                    app.send_event(Event::Update).await;
+        let app = self.clone();
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
+                .listening_to_state_changes(async |_| {
+                    app.send_event(Event::Update(Subsystems::all())).await;
                 })
                 .await
-                .unwrap();
-        })
-    }
+            {
+                error!("Failed to listen to state changes: {}", e);
+            }
+        }));
 
-    pub async fn run(
-        &self,
-        mut event_rx: Receiver<Event>,
-        mut action_rx: Receiver<Action>,
-        mut tray_manager: TrayManager,
-    ) {
         let app = self.clone();
-        tokio::spawn(async move {
-            app.network_manager
-                .listening_to_state_changes(async |_| {
-                    app.send_event(Event::Update).await;
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
+                .listening_to_primary_connection_changes(async || {
+                    app.send_event(Event::PrimaryConnectionChanged).await;
                 })
                 .await
-                .unwrap();
-        });
+            {
+                error!("Failed to listen to primary connection changes: {}", e);
+            }
+        }));
 
         let app = self.clone();
-        tokio::spawn(async move {
-            app.network_manager
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
                 .listening_to_device_added(async |_| {
-                    app.send_event(Event::Update).await;
+                    app.send_event(Event::DevicesChanged).await;
                 })
                 .await
-                .unwrap();
-        });
+            {
+                error!("Failed to listen to added devices: {}", e);
+            }
+        }));
 
         let app = self.clone();
-        tokio::spawn(async move {
-            app.network_manager
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
                 .listening_to_device_removed(async |_| {
-                    app.send_event(Event::Update).await;
+                    app.send_event(Event::DevicesChanged).await;
                 })
                 .await
-                .unwrap();
-        });
-
-        let mut primary_connection_handle = self.setup_primary_connection_listener().await;
+            {
+                error!("Failed to listen to removed devices: {}", e);
+            }
+        }));
 
-        let mut access_points_handle = self.setup_access_points_listener().await;
+        let app = self.clone();
+        handles.push(tokio::spawn(async move {
+            if let Err(e) =
+                properties::listening_to_removed_objects(app.network_manager().connection()).await
+            {
+                error!("Failed to listen to removed objects: {}", e);
+            }
+        }));
 
         let app = self.clone();
-        tokio::spawn(async move {
-            while let Some(action) = action_rx.recv().await {
-                match action {
-                    Action::ChangeAccessPoint(access_point) => {
-                        if let Err(e) = app.change_access_point(access_point).await {
-                            error!("Failed to change access point: {}", e);
-                        };
-                    }
-                    Action::ToggleWifi => {
-                        app.toggle_wifi().await;
-                    }
-                    Action::ToggleWired => {
-                        app.toggle_wired().await;
-                    }
-                    Action::ToggleAirplaneMode => {
-                        app.toggle_airplane_mode().await;
-                    }
-                    Action::ToggleVPN(vpn) => {
-                        app.toggle_vpn(vpn).await;
-                    }
-                    Action::RequestScan => {
-                        if let Err(e) = app.request_scan().await {
-                            error!("Failed to request scan: {}", e);
-                        };
-                    }
+        handles.push(tokio::spawn(async move {
+            let connection = app.network_manager().connection().clone();
+            if let Err(e) = sleep::listening_to_sleep(&connection, async |start| {
+                if !start {
+                    app.send_event(Event::Resumed).await;
                 }
+            })
+            .await
+            {
+                error!("Failed to listen to sleep changes: {}", e);
             }
+        }));
 
-            warn!("Action channel closed");
-        });
-
-        while let Some(event) = event_rx.recv().await {
-            match event {
-                Event::Init => {
-                    if let ControlFlow::Break(_) = self.update(&mut tray_manager).await {
-                        break;
+        let app = self.clone();
+        handles.push(tokio::spawn(async move {
+            if let Err(e) = app
+                .network_manager()
+                .listening_to_service_changes(async |running| {
+                    if running {
+                        app.send_event(Event::NetworkManagerStarted).await;
+                    } else {
+                        app.send_event(Event::NetworkManagerStopped).await;
                     }
+                })
+                .await
+            {
+                error!("Failed to listen to NetworkManager service changes: {}", e);
+            }
 
-                    continue;
-                }
-                Event::Update => {
-                    primary_connection_handle.abort();
-                    primary_connection_handle = self.setup_primary_connection_listener().await;
+            // The stream only ends once the system bus connection is gone.
+            app.send_event(Event::SystemBusDisconnected).await;
+        }));
 
-                    access_points_handle.abort();
-                    access_points_handle = self.setup_access_points_listener().await;
+        handles
+    }
 
-                    if let ControlFlow::Break(_) = self.update(&mut tray_manager).await {
-                        break;
-                    }
+    async fn reconnect_system_bus(&self) {
+        let mut retry_count = 0u32;
 
+        loop {
+            retry_count = retry_count.saturating_add(1);
+            _ = tokio::time::sleep(Duration::from_millis(
+                2_u64.saturating_pow(retry_count).min(16_384),
+            ))
+            .await;
+
+            let connection = match Connection::system().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    warn!(
+                        "Failed to reconnect to system bus: {} (attempt {})",
+                        e, retry_count
+                    );
                     continue;
                 }
-                Event::Shutdown => break,
+            };
+
+            match NetworkManager::new(connection).await {
+                Ok(network_manager) => {
+                    info!("Reconnected to system bus");
+                    self.set_network_manager(network_manager);
+                    self.send_event(Event::NetworkManagerStarted).await;
+                    return;
+                }
+                Err(e) => {
+                    warn!(
+                        "Failed to create NetworkManager proxy: {} (attempt {})",
+                        e, retry_count
+                    );
+                }
+            }
+        }
+    }
+
+    async fn is_network_manager_running(&self) -> bool {
+        match self.network_manager().is_running().await {
+            Ok(running) => running,
+            Err(e) => {
+                error!("Failed to check whether NetworkManager is running: {}", e);
+                false
             }
         }
     }
 
-    async fn update(&self, tray_manager: &mut TrayManager) -> ControlFlow<()> {
+    async fn show_network_manager_not_running(&self, tray_manager: &mut impl TrayManager) {
         tray_manager.update(TrayUpdate::Wireless(None)).await;
         tray_manager.update(TrayUpdate::Wired(None)).await;
         tray_manager.update(TrayUpdate::Vpn(None)).await;
         tray_manager.update(TrayUpdate::AirplaneMode(None)).await;
+        tray_manager.update(TrayUpdate::Networking(None)).await;
+        tray_manager
+            .update(TrayUpdate::PrimaryConnection(None))
+            .await;
+        tray_manager.update(TrayUpdate::Connectivity(None)).await;
+        tray_manager
+            .update(TrayUpdate::Icon(Icon::NotRunning))
+            .await;
+        tray_manager.flush().await;
+
+        systemd::status("NetworkManager is not running");
+        if tray_manager.is_ready() {
+            systemd::ready();
+        }
+    }
 
-        let state = match self.network_manager.state().await {
-            Ok(state) => state,
-            Err(e) => {
+    /// A one-line summary of the network for the service manager.
+    async fn status_line(&self) -> String {
+        let state = self.last_state.read().unwrap().unwrap_or_default();
+
+        match self.network_manager().primary_connection().await {
+            Ok(Some(primary_connection)) => match primary_connection.id() {
+                Ok(id) => format!("{:?} via {}", state, id),
+                Err(_) => format!("{:?}", state),
+            },
+            _ => format!("{:?}", state),
+        }
+    }
+
+    pub async fn run(
+        &self,
+        mut event_rx: Receiver<Event>,
+        mut action_rx: Receiver<Action>,
+        mut tray_manager: impl TrayManager,
+    ) {
+        let mut network_manager_handles = self.setup_network_manager_listeners().await;
+
+        let mut primary_connection_handle = self.setup_primary_connection_listener().await;
+
+        let mut access_points_handle = self.setup_access_points_listener().await;
+        let mut device_state_handle = self.setup_device_state_listener().await;
+
+        let mut network_manager_running = self.is_network_manager_running().await;
+
+        let app = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = rfkill::listening_to_changes(async |_| {
+                // A hardware switch changes the Wi-Fi menu and the icon as well.
+                app.send_event(Event::Update(
+                    Subsystems::AIRPLANE_MODE | Subsystems::WIFI | Subsystems::ICON,
+                ))
+                .await;
+            })
+            .await
+            {
+                error!("Failed to listen to rfkill changes: {}", e);
+            }
+        });
+
+        let app = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = listening_to_config_changes(async || {
+                app.send_event(Event::ConfigChanged).await;
+            })
+            .await
+            {
+                error!("Failed to listen to config changes: {}", e);
+            }
+        });
+
+        let app = self.clone();
+        tokio::spawn(async move {
+            while let Some(action) = action_rx.recv().await {
+                if !app.is_network_manager_running().await {
+                    warn!("NetworkManager is not running. Ignoring {:?}", action);
+                    continue;
+                }
+
+                match action {
+                    Action::ChangeAccessPoint(access_point) => {
+                        let ssid = access_point.ssid.clone();
+                        if let Err(e) = app.change_access_point(access_point).await {
+                            error!("Failed to change access point: {}", e);
+                            app.notify(
+                                Category::ActivationFailed,
+                                format!("Failed to connect to {}", ssid),
+                                e.to_string(),
+                            );
+                        };
+                    }
+                    Action::ConnectTo(ssid) => {
+                        if let Err(e) = app.connect_to(&ssid).await {
+                            error!("Failed to connect to {}: {}", ssid, e);
+                            app.notify(
+                                Category::ActivationFailed,
+                                format!("Failed to connect to {}", ssid),
+                                e.to_string(),
+                            );
+                        }
+                    }
+                    Action::ToggleWifi => {
+                        if let Err(e) = app.toggle_wifi().await {
+                            error!("Failed to toggle Wi-Fi: {}", e);
+                        };
+                    }
+                    Action::ToggleWired => {
+                        if let Err(e) = app.toggle_wired().await {
+                            error!("Failed to toggle wired: {}", e);
+                        };
+                    }
+                    Action::ToggleBluetooth => {
+                        if let Err(e) = app.toggle_bluetooth().await {
+                            error!("Failed to toggle Bluetooth: {}", e);
+                        };
+                    }
+                    Action::ToggleWwan => {
+                        if let Err(e) = app.toggle_wwan().await {
+                            error!("Failed to toggle mobile broadband: {}", e);
+                        };
+                    }
+                    Action::ToggleAirplaneMode => {
+                        app.toggle_airplane_mode().await;
+                    }
+                    Action::ToggleNetworking => {
+                        if let Err(e) = app.toggle_networking().await {
+                            error!("Failed to toggle networking: {}", e);
+                        };
+                    }
+                    Action::ToggleVPN(vpn) => {
+                        if let Err(e) = app.toggle_vpn(vpn.clone()).await {
+                            error!("Failed to toggle VPN {}: {}", vpn, e);
+                            app.notify(
+                                Category::ActivationFailed,
+                                format!("Failed to toggle {}", vpn),
+                                e.to_string(),
+                            );
+                        }
+                    }
+                    Action::RequestScan => {
+                        if let Err(e) = app.request_scan().await {
+                            error!("Failed to request scan: {}", e);
+                        };
+                    }
+                    Action::OpenCaptivePortal => {
+                        if let Err(e) = app.open_captive_portal().await {
+                            error!("Failed to open captive portal: {}", e);
+                        };
+                    }
+                    Action::CheckConnectivity => {
+                        if let Err(e) = app.check_connectivity().await {
+                            error!("Failed to check connectivity: {}", e);
+                        };
+                    }
+                    Action::ToggleConnectivityCheck => {
+                        if let Err(e) = app.toggle_connectivity_check().await {
+                            error!("Failed to toggle connectivity checks: {}", e);
+                        };
+                    }
+                }
+            }
+
+            warn!("Action channel closed");
+        });
+
+        let mut scheduler = UpdateScheduler::new(self.config().update_window());
+        let mut watchdog = Watchdog::new();
+
+        loop {
+            let event = tokio::select! {
+                event = event_rx.recv() => event,
+                // Pinged from here so that a stuck loop stops the pings and gets the applet
+                // restarted.
+                _ = watchdog.tick() => {
+                    systemd::notify("WATCHDOG=1");
+                    continue;
+                }
+                _ = scheduler.ready() => {
+                    let (dirty, requests) = scheduler.take();
+
+                    if !network_manager_running {
+                        continue;
+                    }
+
+                    info!("Updating {:?} ({} requests coalesced)", dirty, requests);
+
+                    let flow = self.update(&mut tray_manager, dirty).await;
+                    tray_manager.flush().await;
+
+                    if let ControlFlow::Break(_) = flow {
+                        if self.is_network_manager_running().await {
+                            break;
+                        }
+
+                        // NetworkManager went away mid-update. Wait for it to come back instead of
+                        // exiting; the service listener will report when it does.
+                        warn!("NetworkManager is no longer available");
+                        network_manager_running = false;
+                        self.show_network_manager_not_running(&mut tray_manager)
+                            .await;
+                    } else if systemd::is_enabled() {
+                        systemd::status(&self.status_line().await);
+                    }
+
+                    // Without a StatusNotifierHost the trays aren't registered yet. The update that
+                    // follows one registering gets here again.
+                    if tray_manager.is_ready() {
+                        systemd::ready();
+                    }
+
+                    continue;
+                }
+            };
+
+            let Some(event) = event else {
+                break;
+            };
+
+            match event {
+                Event::Init => {
+                    if !network_manager_running {
+                        warn!("NetworkManager is not running");
+                        self.show_network_manager_not_running(&mut tray_manager)
+                            .await;
+                        continue;
+                    }
+                }
+                Event::Update(subsystems) => {
+                    if !network_manager_running {
+                        continue;
+                    }
+
+                    scheduler.schedule(subsystems);
+                    continue;
+                }
+                Event::DevicesChanged => {
+                    if !network_manager_running {
+                        continue;
+                    }
+
+                    // The subscriptions only cover the devices that were around when they were made.
+                    device_state_handle.abort();
+                    device_state_handle = self.setup_device_state_listener().await;
+
+                    access_points_handle.abort();
+                    access_points_handle = self.setup_access_points_listener().await;
+                }
+                Event::PrimaryConnectionChanged => {
+                    if !network_manager_running {
+                        continue;
+                    }
+
+                    primary_connection_handle.abort();
+                    primary_connection_handle = self.setup_primary_connection_listener().await;
+                }
+                Event::NetworkManagerStarted | Event::Resumed => {
+                    match event {
+                        Event::Resumed => info!("Resumed from sleep"),
+                        _ => info!("NetworkManager started"),
+                    }
+
+                    // Proxies and signal subscriptions are bound to the previous instance, so
+                    // rebuild them against whichever connection is current.
+                    properties::clear_cache();
+                    let connection = self.network_manager().connection().clone();
+                    match NetworkManager::new(connection).await {
+                        Ok(network_manager) => self.set_network_manager(network_manager),
+                        Err(e) => {
+                            error!("Failed to create NetworkManager proxy: {}", e);
+                            continue;
+                        }
+                    };
+
+                    for handle in network_manager_handles.drain(..) {
+                        handle.abort();
+                    }
+                    network_manager_handles = self.setup_network_manager_listeners().await;
+
+                    primary_connection_handle.abort();
+                    primary_connection_handle = self.setup_primary_connection_listener().await;
+
+                    access_points_handle.abort();
+                    access_points_handle = self.setup_access_points_listener().await;
+
+                    device_state_handle.abort();
+                    device_state_handle = self.setup_device_state_listener().await;
+
+                    network_manager_running = self.is_network_manager_running().await;
+                    if !network_manager_running {
+                        self.show_network_manager_not_running(&mut tray_manager)
+                            .await;
+                        continue;
+                    }
+                }
+                Event::NetworkManagerStopped => {
+                    warn!("NetworkManager stopped");
+                    network_manager_running = false;
+
+                    primary_connection_handle.abort();
+                    access_points_handle.abort();
+                    device_state_handle.abort();
+
+                    self.show_network_manager_not_running(&mut tray_manager)
+                        .await;
+                    continue;
+                }
+                Event::SystemBusDisconnected => {
+                    warn!("System bus connection lost");
+                    network_manager_running = false;
+
+                    for handle in network_manager_handles.drain(..) {
+                        handle.abort();
+                    }
+                    primary_connection_handle.abort();
+                    access_points_handle.abort();
+                    device_state_handle.abort();
+
+                    self.show_network_manager_not_running(&mut tray_manager)
+                        .await;
+
+                    let app = self.clone();
+                    tokio::spawn(async move {
+                        app.reconnect_system_bus().await;
+                    });
+                    continue;
+                }
+                Event::StatusNotifierHostRegistered => {
+                    tray_manager.refresh().await;
+
+                    if !network_manager_running {
+                        self.show_network_manager_not_running(&mut tray_manager)
+                            .await;
+                        continue;
+                    }
+                }
+                Event::ConfigChanged => {
+                    match Config::load() {
+                        Ok(config) => {
+                            info!("Config reloaded");
+                            scheduler.set_window(config.update_window());
+                            self.set_config(config);
+                            clear_icon_cache();
+                            tray_manager.refresh().await;
+                        }
+                        Err(e) => {
+                            error!("Keeping the current config: {:#}", e);
+                            continue;
+                        }
+                    }
+
+                    if !network_manager_running {
+                        continue;
+                    }
+                }
+                Event::Shutdown => break,
+            }
+
+            scheduler.schedule(Subsystems::all());
+        }
+    }
+
+    async fn update(
+        &self,
+        tray_manager: &mut impl TrayManager,
+        dirty: Subsystems,
+    ) -> ControlFlow<()> {
+        if dirty.contains(Subsystems::WIFI) {
+            tray_manager.update(TrayUpdate::Wireless(None)).await;
+        }
+        if dirty.contains(Subsystems::WIRED) {
+            tray_manager.update(TrayUpdate::Wired(None)).await;
+        }
+        if dirty.contains(Subsystems::VPN) {
+            tray_manager.update(TrayUpdate::Vpn(None)).await;
+        }
+        if dirty.contains(Subsystems::AIRPLANE_MODE) {
+            tray_manager.update(TrayUpdate::AirplaneMode(None)).await;
+        }
+        if dirty.contains(Subsystems::NETWORKING) {
+            tray_manager.update(TrayUpdate::Networking(None)).await;
+        }
+
+        let state = match self.network_manager().state().await {
+            Ok(state) => state,
+            Err(e) => {
                 error!("Failed to get state: {}", e);
                 return ControlFlow::Break(());
             }
@@ -516,407 +1276,1071 @@ impl App {
 
         info!("State: {:?}", state);
 
-        let is_airplane_mode = match self.network_manager.airplane_mode_enabled().await {
-            Ok(is_airplane_mode) => is_airplane_mode,
+        let previous_state = self.last_state.write().unwrap().replace(state);
+        if let Some(previous_state) = previous_state {
+            self.notify_state_change(previous_state, state).await;
+        }
+
+        if dirty.contains(Subsystems::AIRPLANE_MODE) {
+            tray_manager
+                .update(TrayUpdate::AirplaneMode(Some(AirplaneModeState {
+                    on: airplane_mode::is_enabled(),
+                    radios: self.radio_states().await,
+                })))
+                .await;
+        }
+
+        if dirty.contains(Subsystems::NETWORKING) {
+            let networking_enabled = match self.network_manager().networking_enabled().await {
+                Ok(networking_enabled) => networking_enabled,
+                Err(e) => {
+                    error!("Failed to get whether networking is enabled: {}", e);
+                    return ControlFlow::Break(());
+                }
+            };
+
+            tray_manager
+                .update(TrayUpdate::Networking(Some(NetworkingState {
+                    on: networking_enabled,
+                })))
+                .await;
+        }
+
+        if let ControlFlow::Break(_) = self.update_icon(tray_manager, dirty, state).await {
+            return ControlFlow::Break(());
+        }
+
+        if !dirty.intersects(Subsystems::WIFI | Subsystems::WIRED | Subsystems::VPN) {
+            return ControlFlow::Continue(());
+        }
+
+        let devices = match self.network_manager().devices().await {
+            Ok(devices) => devices,
             Err(e) => {
-                error!("Failed to get airplane mode: {}", e);
+                error!("Failed to get devices: {}", e);
                 return ControlFlow::Break(());
             }
         };
 
-        if is_airplane_mode {
-            tray_manager
-                .update(TrayUpdate::AirplaneMode(Some(AirplaneModeState {
-                    on: true,
-                })))
-                .await;
-        } else {
-            tray_manager
-                .update(TrayUpdate::AirplaneMode(Some(AirplaneModeState {
-                    on: false,
-                })))
-                .await;
+        let config = self.config();
+
+        let mut vpn_connections = Vec::<VPNConnection>::new();
+
+        for device in devices {
+            let device_type = match device.device_type() {
+                Ok(device_type) => device_type,
+                Err(e) => {
+                    warn!("Failed to get device type: {}", e);
+                    continue;
+                }
+            };
+
+            let interface = device.interface().unwrap_or_default();
+            if config.devices.is_hidden(device_type, &interface) {
+                continue;
+            }
+
+            match device_type {
+                DeviceType::Wifi if dirty.contains(Subsystems::WIFI) => {
+                    let state = match device.state() {
+                        Ok(state) => state,
+                        Err(e) => {
+                            warn!("Failed to get Wi-Fi device state: {}", e);
+                            continue;
+                        }
+                    };
+
+                    match state {
+                        state if state == DeviceState::Activated || state.is_activating() => {
+                            let wireless_device = match device.to_specific_device().await {
+                                Some(SpecificDevice::Wireless(device)) => device,
+                                _ => return ControlFlow::Break(()),
+                            };
+
+                            let configured_connections = match device.available_connections().await
+                            {
+                                Ok(configured_connections) => configured_connections,
+                                Err(e) => {
+                                    warn!("Failed to get configured connections: {}", e);
+                                    vec![]
+                                }
+                            };
+                            let futures = configured_connections.iter().map(|setting| setting.id());
+
+                            let ssids_of_known_connections = HashSet::<String>::from_iter(
+                                futures::future::join_all(futures)
+                                    .await
+                                    .into_iter()
+                                    .filter_map(Result::ok),
+                            );
+
+                            let access_points = match wireless_device.access_points().await {
+                                Ok(access_points) => access_points,
+                                Err(e) => {
+                                    error!("Failed to get access points: {}", e);
+                                    vec![]
+                                }
+                            };
+                            let mut available_connections = access_points
+                                .iter()
+                                .filter_map(|ap| {
+                                    Some(WifiConnection {
+                                        ssid: ap.id().ok()?,
+                                        hw_address: ap.hw_address().ok()?,
+                                        strength: ap.strength().unwrap_or_default(),
+                                        secure: ap.secure().unwrap_or_default(),
+                                    })
+                                })
+                                .collect::<Vec<WifiConnection>>();
+
+                            let known_connections = ssids_of_known_connections
+                                .iter()
+                                .map(|ssid| {
+                                    match available_connections
+                                        .iter()
+                                        .find(|connection| *connection.ssid == *ssid)
+                                    {
+                                        Some(connection) => connection.clone(),
+                                        None => WifiConnection {
+                                            ssid: ssid.clone(),
+                                            hw_address: "".into(),
+                                            strength: 0,
+                                            secure: false,
+                                        },
+                                    }
+                                })
+                                .collect::<Vec<WifiConnection>>()
+                                .clone();
+
+                            let known_connections = known_connections
+                                .clone()
+                                .into_iter()
+                                .filter(|connection| !connection.hw_address.is_empty())
+                                .collect::<Vec<WifiConnection>>();
+
+                            available_connections.sort_by(|a, b| {
+                                for key in &config.wifi.sort {
+                                    let ordering = match key {
+                                        SortKey::Known => ssids_of_known_connections
+                                            .contains(&b.ssid)
+                                            .cmp(&ssids_of_known_connections.contains(&a.ssid)),
+                                        SortKey::Strength => b.strength.cmp(&a.strength),
+                                        SortKey::Security => b.secure.cmp(&a.secure),
+                                        SortKey::Name => {
+                                            a.ssid.to_lowercase().cmp(&b.ssid.to_lowercase())
+                                        }
+                                    };
+
+                                    if ordering != Ordering::Equal {
+                                        return ordering;
+                                    }
+                                }
+
+                                Ordering::Equal
+                            });
+
+                            if let Some(max_networks) = config.wifi.max_networks {
+                                available_connections.truncate(max_networks);
+                            }
+
+                            let active_connection_id = device
+                                .active_connection()
+                                .await
+                                .ok()
+                                .flatten()
+                                .and_then(|active_connection| active_connection.id().ok());
+
+                            let active_connection_index = active_connection_id
+                                .as_ref()
+                                .and_then(|active_connection_id| {
+                                    known_connections.iter().position(|connection| {
+                                        connection.ssid == *active_connection_id
+                                    })
+                                })
+                                .unwrap_or_default();
+
+                            let active_connection =
+                                active_connection_id
+                                    .as_ref()
+                                    .and_then(|active_connection_id| {
+                                        known_connections
+                                            .iter()
+                                            .find(|connection| {
+                                                connection.ssid == *active_connection_id
+                                            })
+                                            .cloned()
+                                    });
+
+                            let activation = match (activation_stage(state), active_connection_id) {
+                                (Some(stage), Some(ssid)) => Some(WifiActivation { ssid, stage }),
+                                _ => None,
+                            };
+
+                            tray_manager
+                                .update(TrayUpdate::Wireless(Some(WifiState {
+                                    on: true,
+                                    active_connection,
+                                    active_connection_index,
+                                    known_connections,
+                                    available_connections,
+                                    activation,
+                                    failure: None,
+                                    hardware_blocked: false,
+                                })))
+                                .await;
+                        }
+                        DeviceState::Disconnected => {
+                            let on = match self.network_manager().wifi_enabled().await {
+                                Ok(on) => on,
+                                Err(e) => {
+                                    warn!("Failed to get whether Wi-Fi is enabled: {}", e);
+                                    false
+                                }
+                            };
+                            tray_manager
+                                .update(TrayUpdate::Wireless(Some(WifiState {
+                                    on,
+                                    active_connection: None,
+                                    active_connection_index: 0,
+                                    known_connections: vec![],
+                                    available_connections: vec![],
+                                    activation: None,
+                                    failure: failure(&device),
+                                    hardware_blocked: false,
+                                })))
+                                .await;
+
+                            // The device state listener schedules an update once the device
+                            // activates.
+                        }
+                        DeviceState::Unavailable | DeviceState::Unmanaged => {
+                            // Often happens when the wifi is disabled or airplane mode is on.
+                            // While asleep, NetworkManager unmanages every device.
+                            info!("Wireless Device state: {:?}", state);
+                            tray_manager
+                                .update(TrayUpdate::Wireless(Some(WifiState {
+                                    on: false,
+                                    active_connection: None,
+                                    active_connection_index: 0,
+                                    known_connections: vec![],
+                                    available_connections: vec![],
+                                    activation: None,
+                                    failure: None,
+                                    hardware_blocked: self.wifi_hardware_blocked().await,
+                                })))
+                                .await;
+                        }
+                        _ => {}
+                    }
+                }
+                DeviceType::Ethernet if dirty.contains(Subsystems::WIRED) => {
+                    let state = match device.state() {
+                        Ok(state) => state,
+                        Err(e) => {
+                            warn!("Failed to get Ethernet device state: {}", e);
+                            continue;
+                        }
+                    };
+
+                    match state {
+                        DeviceState::Activated => {
+                            tray_manager
+                                .update(TrayUpdate::Wired(Some(WiredState {
+                                    on: true,
+                                    stage: None,
+                                    failure: None,
+                                })))
+                                .await;
+                        }
+                        state if state.is_activating() => {
+                            tray_manager
+                                .update(TrayUpdate::Wired(Some(WiredState {
+                                    on: true,
+                                    stage: activation_stage(state),
+                                    failure: None,
+                                })))
+                                .await;
+                        }
+                        DeviceState::Disconnected => {
+                            tray_manager
+                                .update(TrayUpdate::Wired(Some(WiredState {
+                                    on: false,
+                                    stage: None,
+                                    failure: failure(&device),
+                                })))
+                                .await;
+                        }
+
+                        _ => {}
+                    }
+                }
+                DeviceType::WireGuard if dirty.contains(Subsystems::VPN) => {
+                    let wire_guard_connection = match device.active_connection().await {
+                        Ok(Some(wire_guard_connection)) => wire_guard_connection,
+                        Ok(None) => continue,
+                        Err(e) => {
+                            warn!("Failed to get WireGuard connection: {}", e);
+                            continue;
+                        }
+                    };
+                    let wire_guard_connection_id = match wire_guard_connection.id() {
+                        Ok(id) => id,
+                        Err(e) => {
+                            warn!("Failed to get WireGuard connection name: {}", e);
+                            continue;
+                        }
+                    };
+                    let state = match device.state() {
+                        Ok(state) => state,
+                        Err(e) => {
+                            warn!("Failed to get WireGuard device state: {}", e);
+                            continue;
+                        }
+                    };
+
+                    match state {
+                        DeviceState::Activated => {
+                            let endpoint = match wire_guard_connection.settings().await {
+                                Ok(settings) => settings
+                                    .wireguard_endpoints()
+                                    .await
+                                    .unwrap_or_default()
+                                    .into_iter()
+                                    .next(),
+                                Err(e) => {
+                                    warn!("Failed to get VPN connection settings: {}", e);
+                                    None
+                                }
+                            };
+
+                            let connected_since = *self
+                                .vpn_connected_since
+                                .write()
+                                .unwrap()
+                                .entry(wire_guard_connection_id.clone())
+                                .or_insert_with(|| {
+                                    if previous_state.is_some() {
+                                        self.notify(
+                                            Category::Vpn,
+                                            "VPN connected",
+                                            wire_guard_connection_id.clone(),
+                                        );
+                                    }
+                                    SystemTime::now()
+                                });
+
+                            let vpn_connection = VPNConnection {
+                                name: wire_guard_connection_id.clone(),
+                                on: true,
+                                stage: None,
+                                endpoint,
+                                connected_since: Some(connected_since),
+                            };
+                            vpn_connections.push(vpn_connection);
+                        }
+                        state if state.is_activating() => {
+                            let vpn_connection = VPNConnection {
+                                name: wire_guard_connection_id.clone(),
+                                on: true,
+                                stage: activation_stage(state),
+                                endpoint: None,
+                                connected_since: None,
+                            };
+                            vpn_connections.push(vpn_connection);
+                        }
+                        DeviceState::Disconnected | DeviceState::Unavailable => {
+                            let vpn_connection = VPNConnection {
+                                name: wire_guard_connection_id.clone(),
+                                on: false,
+                                stage: None,
+                                endpoint: None,
+                                connected_since: None,
+                            };
+
+                            vpn_connections.push(vpn_connection);
+                        }
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if dirty.contains(Subsystems::VPN) {
+            self.vpn_connected_since.write().unwrap().retain(|name, _| {
+                let connected = vpn_connections
+                    .iter()
+                    .any(|connection| connection.name == *name && connection.stage.is_none());
+                if !connected {
+                    self.notify(Category::Vpn, "VPN disconnected", name.clone());
+                }
+                connected
+            });
+        }
+
+        if !vpn_connections.is_empty() {
+            tray_manager
+                .update(TrayUpdate::Vpn(Some(VPNState {
+                    connections: vpn_connections,
+                })))
+                .await;
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// Updates the icon, the primary connection and connectivity. The device sections are left to
+    /// `update`, so returning early here doesn't keep them from showing while connecting.
+    async fn update_icon(
+        &self,
+        tray_manager: &mut impl TrayManager,
+        dirty: Subsystems,
+        state: NmState,
+    ) -> ControlFlow<()> {
+        let state_icon = match state {
+            NmState::Unknown => Some(Icon::Unknown),
+            NmState::Asleep | NmState::Disconnected if self.wifi_hardware_blocked().await => {
+                Some(Icon::HardwareDisabled)
+            }
+            NmState::Asleep => Some(Icon::Off),
+            NmState::Connecting | NmState::Disconnecting => {
+                if let Ok(Some(activating_connection)) =
+                    self.network_manager().activating_connection().await
+                {
+                    info!("Activating connection: {:?}", activating_connection.id());
+                }
+                Some(Icon::Busy)
+            }
+            NmState::Disconnected => Some(Icon::Disconnected),
+            _ => None,
+        };
+
+        if let Some(icon) = state_icon {
+            *self.last_connectivity.write().unwrap() = None;
+
+            if dirty.contains(Subsystems::ICON) {
+                tray_manager
+                    .update(TrayUpdate::PrimaryConnection(None))
+                    .await;
+                tray_manager.update(TrayUpdate::Connectivity(None)).await;
+                tray_manager.update(TrayUpdate::Icon(icon)).await;
+            }
+            return ControlFlow::Continue(());
+        }
+
+        let connectivity = match self.network_manager().connectivity().await {
+            Ok(connectivity) => connectivity,
+            Err(e) => {
+                error!("Failed to get connectivity: {}", e);
+                return ControlFlow::Break(());
+            }
+        };
+
+        info!("Connectivity: {:?}", connectivity);
+
+        let previous_connectivity = self
+            .last_connectivity
+            .write()
+            .unwrap()
+            .replace(connectivity);
+        self.notify_connectivity_change(previous_connectivity, connectivity);
+
+        if dirty.contains(Subsystems::ICON) {
+            match self.connectivity_state(connectivity).await {
+                Ok(connectivity_state) => {
+                    tray_manager
+                        .update(TrayUpdate::Connectivity(Some(connectivity_state)))
+                        .await;
+                }
+                Err(e) => {
+                    error!("Failed to get connectivity state: {}", e);
+                    return ControlFlow::Break(());
+                }
+            }
+        }
+
+        let connectivity_badges = match connectivity {
+            NmConnectivityState::Unknown => {
+                if dirty.contains(Subsystems::ICON) {
+                    tray_manager
+                        .update(TrayUpdate::PrimaryConnection(None))
+                        .await;
+                    tray_manager.update(TrayUpdate::Icon(Icon::Unknown)).await;
+                }
+                None
+            }
+            NmConnectivityState::None => {
+                if dirty.contains(Subsystems::ICON) {
+                    tray_manager
+                        .update(TrayUpdate::PrimaryConnection(None))
+                        .await;
+                    tray_manager
+                        .update(TrayUpdate::Icon(Icon::Disconnected))
+                        .await;
+                }
+                return ControlFlow::Continue(());
+            }
+            NmConnectivityState::Portal => Some(Badges::CAPTIVE_PORTAL),
+            NmConnectivityState::Loss => {
+                if dirty.contains(Subsystems::ICON) {
+                    let app = self.clone();
+                    tokio::spawn(async move {
+                        let mut retry_count = 0u32;
+
+                        loop {
+                            let status = match app.network_manager().check_connectivity().await {
+                                Ok(status) => status,
+                                Err(e) => {
+                                    error!("Failed to check connectivity: {}", e);
+                                    return;
+                                }
+                            };
+
+                            if matches!(status, NmConnectivityState::Full) {
+                                app.send_event(Event::Update(Subsystems::ICON)).await;
+                                break;
+                            }
+
+                            if retry_count >= 16 {
+                                break;
+                            }
+
+                            retry_count = retry_count.saturating_add(1);
+                            _ = tokio::time::sleep(Duration::from_millis(
+                                2_u64.saturating_pow(retry_count).min(65_536),
+                            ))
+                            .await;
+                        }
+                    });
+                }
+                Some(Badges::LIMITED)
+            }
+            NmConnectivityState::Full => Some(Badges::empty()),
+        };
+
+        if let Some(badges) = connectivity_badges
+            && dirty.contains(Subsystems::ICON)
+        {
+            match self.network_manager().primary_connection().await {
+                Ok(Some(primary_connection)) => {
+                    if let ControlFlow::Break(_) = self
+                        .update_primary_connection_icon(tray_manager, primary_connection, badges)
+                        .await
+                    {
+                        return ControlFlow::Break(());
+                    }
+                }
+                Ok(None) => {
+                    info!("No primary connection");
+                    tray_manager
+                        .update(TrayUpdate::PrimaryConnection(None))
+                        .await;
+                    let icon = if badges.contains(Badges::LIMITED) {
+                        Icon::Limited
+                    } else {
+                        Icon::Disconnected
+                    };
+                    tray_manager.update(TrayUpdate::Icon(icon)).await;
+                }
+                Err(e) => {
+                    error!("Failed to get primary connection: {}", e);
+                    return ControlFlow::Break(());
+                }
+            };
+        }
+
+        ControlFlow::Continue(())
+    }
+
+    /// The state of every kind of radio the machine has.
+    async fn radio_states(&self) -> Vec<RadioState> {
+        let network_manager = self.network_manager();
+        let mut radios = Vec::new();
+
+        let radio_flags = match network_manager.radio_flags().await {
+            Ok(radio_flags) => radio_flags,
+            Err(e) => {
+                warn!("Failed to get available radios: {}", e);
+                RadioFlags::empty()
+            }
+        };
+
+        if radio_flags.contains(RadioFlags::WLAN_AVAILABLE) {
+            match (
+                network_manager.wifi_enabled().await,
+                network_manager.wifi_hardware_enabled().await,
+            ) {
+                (Ok(enabled), Ok(hardware_enabled)) => radios.push(RadioState {
+                    kind: RadioKind::Wifi,
+                    on: enabled && hardware_enabled,
+                    hardware_blocked: !hardware_enabled,
+                }),
+                (Err(e), _) | (_, Err(e)) => warn!("Failed to get Wi-Fi radio state: {}", e),
+            }
+        }
+
+        match rfkill::radios_of_type(RadioType::Bluetooth) {
+            Ok(bluetooth) if !bluetooth.is_empty() => radios.push(RadioState {
+                kind: RadioKind::Bluetooth,
+                on: bluetooth
+                    .iter()
+                    .any(|radio| !radio.soft_blocked && !radio.hard_blocked),
+                hardware_blocked: bluetooth.iter().all(|radio| radio.hard_blocked),
+            }),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to get Bluetooth radio state: {:#}", e),
+        }
+
+        if radio_flags.contains(RadioFlags::WWAN_AVAILABLE) {
+            match (
+                network_manager.wwan_enabled().await,
+                network_manager.wwan_hardware_enabled().await,
+            ) {
+                (Ok(enabled), Ok(hardware_enabled)) => radios.push(RadioState {
+                    kind: RadioKind::Wwan,
+                    on: enabled && hardware_enabled,
+                    hardware_blocked: !hardware_enabled,
+                }),
+                (Err(e), _) | (_, Err(e)) => {
+                    warn!("Failed to get mobile broadband radio state: {}", e)
+                }
+            }
+        }
+
+        radios
+    }
+
+    /// Whether the machine has Wi-Fi and a hardware switch has it turned off.
+    async fn wifi_hardware_blocked(&self) -> bool {
+        let network_manager = self.network_manager();
+
+        match network_manager.radio_flags().await {
+            Ok(radio_flags) if radio_flags.contains(RadioFlags::WLAN_AVAILABLE) => {}
+            _ => return false,
+        }
+
+        match network_manager.wifi_hardware_enabled().await {
+            Ok(enabled) => !enabled,
+            Err(e) => {
+                warn!("Failed to get whether Wi-Fi is enabled in hardware: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn connectivity_state(
+        &self,
+        connectivity: NmConnectivityState,
+    ) -> Result<ConnectivityState> {
+        let network_manager = self.network_manager();
+
+        let device = match network_manager.primary_connection().await? {
+            Some(primary_connection) => primary_connection.devices().await?.into_iter().next(),
+            None => None,
+        };
+
+        let (ip4, ip6) = match device {
+            Some(device) => (
+                Some(tray_connectivity(device.ip4_connectivity()?)),
+                Some(tray_connectivity(device.ip6_connectivity()?)),
+            ),
+            None => (None, None),
+        };
+
+        Ok(ConnectivityState {
+            connectivity: tray_connectivity(connectivity),
+            ip4,
+            ip6,
+            check_available: network_manager.connectivity_check_available().await?,
+            check_enabled: network_manager.connectivity_check_enabled().await?,
+        })
+    }
+
+    async fn notify_state_change(&self, previous_state: NmState, state: NmState) {
+        let connected = |state| {
+            matches!(
+                state,
+                NmState::ConnectedLocal | NmState::ConnectedSite | NmState::ConnectedGlobal
+            )
+        };
+
+        if connected(state) && !connected(previous_state) {
+            let id = match self.network_manager().primary_connection().await {
+                Ok(Some(primary_connection)) => primary_connection.id().unwrap_or_default(),
+                _ => String::new(),
+            };
+            self.notify(Category::Connection, "Connected", id);
+        } else if state == NmState::Disconnected && connected(previous_state) {
+            self.notify(Category::Connection, "Disconnected", "");
+        }
+    }
+
+    fn notify_connectivity_change(
+        &self,
+        previous_connectivity: Option<NmConnectivityState>,
+        connectivity: NmConnectivityState,
+    ) {
+        if previous_connectivity == Some(connectivity) {
+            return;
         }
 
-        match state {
-            NmState::Unknown => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Unknown)).await;
-                return ControlFlow::Continue(());
-            }
-            NmState::Asleep => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Off)).await;
-                return ControlFlow::Continue(());
+        match connectivity {
+            NmConnectivityState::Portal => {
+                self.notify(
+                    Category::CaptivePortal,
+                    "Sign-in required",
+                    "The network requires signing in before it can reach the internet",
+                );
             }
-            NmState::Connecting | NmState::Disconnecting => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Busy)).await;
-                return ControlFlow::Continue(());
+            NmConnectivityState::Loss if previous_connectivity.is_some() => {
+                self.notify(
+                    Category::Connectivity,
+                    "Limited connectivity",
+                    "The network is reachable but the internet is not",
+                );
             }
-            NmState::Disconnected => {
-                tray_manager
-                    .update(TrayUpdate::Icon(Icon::Disconnected))
-                    .await;
-                return ControlFlow::Continue(());
+            NmConnectivityState::None if previous_connectivity.is_some() => {
+                self.notify(
+                    Category::Connectivity,
+                    "Connectivity lost",
+                    "The network can no longer be reached",
+                );
             }
             _ => {}
+        }
+    }
+
+    async fn update_primary_connection_icon(
+        &self,
+        tray_manager: &mut impl TrayManager,
+        primary_connection: ActiveConnection,
+        badges: Badges,
+    ) -> ControlFlow<()> {
+        let id = primary_connection.id().unwrap_or_default();
+        info!("Primary connection: {:?}", id);
+
+        let addresses = match primary_connection.ip4_config().await {
+            Ok(Some(ip4_config)) => ip4_config.addresses().await.unwrap_or_default(),
+            _ => vec![],
         };
+        info!("Primary connection addresses: {:?}", addresses);
 
-        let connectivity = match self.network_manager.connectivity().await {
-            Ok(connectivity) => connectivity,
+        let (icon, speed) = match self.connected_icon(primary_connection, badges).await {
+            Ok(connected_icon) => connected_icon,
             Err(e) => {
-                error!("Failed to get connectivity: {}", e);
+                error!("Failed to get connected icon: {}", e);
                 return ControlFlow::Break(());
             }
         };
 
-        info!("Connectivity: {:?}", connectivity);
+        info!("Icon: {:?}", icon);
 
-        match connectivity {
-            NmConnectivityState::Unknown => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Unknown)).await;
-            }
-            NmConnectivityState::None => {
-                tray_manager
-                    .update(TrayUpdate::Icon(Icon::Disconnected))
-                    .await;
-                return ControlFlow::Continue(());
-            }
-            NmConnectivityState::Loss => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Limited)).await;
-                let app = self.clone();
-                tokio::spawn(async move {
-                    let mut retry_count = 0u32;
-
-                    loop {
-                        let status = match app.network_manager.check_connectivity().await {
-                            Ok(status) => status,
-                            Err(e) => {
-                                error!("Failed to check connectivity: {}", e);
-                                return;
-                            }
-                        };
+        tray_manager
+            .update(TrayUpdate::PrimaryConnection(Some(
+                PrimaryConnectionState {
+                    id,
+                    addresses,
+                    speed,
+                },
+            )))
+            .await;
+        tray_manager.update(TrayUpdate::Icon(icon)).await;
 
-                        if matches!(status, NmConnectivityState::Full) {
-                            app.send_event(Event::Update).await;
-                            break;
-                        }
+        ControlFlow::Continue(())
+    }
 
-                        if retry_count >= 16 {
-                            break;
-                        }
+    /// Picks a single icon for the current connections: the device carrying the primary
+    /// connection, or failing that any other connected device, as the base, with the VPN badge
+    /// added when a VPN is up on top of it. Shows the busy icon while any connection activates.
+    ///
+    /// Also returns the speed of the base device in Mb/s when it is known.
+    async fn connected_icon(
+        &self,
+        primary_connection: ActiveConnection,
+        mut badges: Badges,
+    ) -> Result<(Icon, Option<u32>)> {
+        let mut active_connections = vec![primary_connection];
+        active_connections.extend(self.network_manager().active_connections().await?);
 
-                        retry_count = retry_count.saturating_add(1);
-                        _ = tokio::time::sleep(Duration::from_millis(
-                            2_u64.saturating_pow(retry_count).min(65_536),
-                        ))
-                        .await;
-                    }
-                });
-            }
-            NmConnectivityState::Full => {}
-            _ => {
-                tray_manager.update(TrayUpdate::Icon(Icon::Unknown)).await;
-                return ControlFlow::Continue(());
-            }
-        }
+        let mut base = None;
+        let mut speed = None;
+        let mut vpn = false;
 
-        if matches!(connectivity, NmConnectivityState::Full) {
-            let primary_connection = match self.network_manager.primary_connection().await {
-                Ok(primary_connection) => primary_connection,
-                Err(e) => {
-                    error!("Failed to get primary connection: {}", e);
-                    return ControlFlow::Break(());
+        for active_connection in active_connections {
+            match active_connection.state()? {
+                ActiveConnectionState::Activated => {}
+                ActiveConnectionState::Activating => {
+                    info!("Activating connection: {:?}", active_connection.id());
+                    return Ok((Icon::Busy, None));
                 }
-            };
-
-            info!("Primary connection: {:?}", primary_connection.id().await);
+                _ => continue,
+            }
 
-            let devices = match primary_connection.devices().await {
-                Ok(devices) => devices,
-                Err(e) => {
-                    error!("Failed to get devices: {}", e);
-                    return ControlFlow::Break(());
-                }
-            };
+            if active_connection.vpn()? {
+                vpn = true;
+            }
 
-            for device in devices {
-                let device_type = match device.device_type().await {
-                    Ok(device_type) => device_type,
-                    Err(e) => {
-                        warn!("Failed to get device type: {}", e);
+            for device in active_connection.devices().await? {
+                let (device_base, device_speed) = match device.device_type()? {
+                    DeviceType::WireGuard | DeviceType::TunTap => {
+                        vpn = true;
                         continue;
                     }
-                };
-
-                match device_type {
+                    DeviceType::Ethernet => {
+                        let device_speed = match device.to_specific_device().await {
+                            Some(SpecificDevice::Wired(wired_device)) => {
+                                wired_device.speed().ok().filter(|speed| *speed > 0)
+                            }
+                            _ => None,
+                        };
+                        (Base::Ethernet, device_speed)
+                    }
+                    DeviceType::Modem => (Base::Cellular, None),
                     DeviceType::Wifi => {
                         let wireless_device = match device.to_specific_device().await {
                             Some(SpecificDevice::Wireless(device)) => device,
-                            _ => return ControlFlow::Break(()),
+                            _ => continue,
                         };
 
-                        let active_access_point =
-                            wireless_device.active_access_point().await.unwrap();
+                        let device_speed = wireless_device
+                            .bitrate()
+                            .ok()
+                            .filter(|bitrate| *bitrate > 0)
+                            .map(|bitrate| bitrate / 1000);
 
-                        let strength = active_access_point.strength().await.unwrap();
-
-                        tray_manager
-                            .update(TrayUpdate::Icon(Icon::Wifi(strength)))
-                            .await;
-                    }
-                    DeviceType::Ethernet => {
-                        tray_manager.update(TrayUpdate::Icon(Icon::Ethernet)).await;
-                    }
-                    DeviceType::TunTap => {
-                        tray_manager.update(TrayUpdate::Icon(Icon::Tun)).await;
-                    }
-                    _ => {
-                        tray_manager.update(TrayUpdate::Icon(Icon::Unknown)).await;
+                        match wireless_device.active_access_point().await? {
+                            Some(active_access_point) => {
+                                (Base::Wifi(active_access_point.strength()?), device_speed)
+                            }
+                            None => {
+                                info!("No active access point");
+                                continue;
+                            }
+                        }
                     }
-                }
-            }
-        }
-
-        let devices = match self.network_manager.all_devices().await {
-            Ok(devices) => devices,
-            Err(e) => {
-                error!("Failed to get devices: {}", e);
-                return ControlFlow::Break(());
-            }
-        };
-
-        let mut vpn_connections = Vec::<VPNConnection>::new();
+                    _ => continue,
+                };
 
-        for device in devices {
-            let device_type = match device.device_type().await {
-                Ok(device_type) => device_type,
-                Err(e) => {
-                    warn!("Failed to get device type: {}", e);
+                if base.is_some() {
                     continue;
                 }
-            };
-
-            match device_type {
-                DeviceType::Wifi => {
-                    let state = device.state().await.unwrap();
-
-                    match state {
-                        DeviceState::Activated => {
-                            let wireless_device = match device.to_specific_device().await {
-                                Some(SpecificDevice::Wireless(device)) => device,
-                                _ => return ControlFlow::Break(()),
-                            };
-
-                            let configured_connections =
-                                device.available_connections().await.unwrap();
-                            let futures = configured_connections
-                                .iter()
-                                .map(|setting| async { setting.id().await.unwrap() });
 
-                            let ssids_of_known_connections = HashSet::<String>::from_iter(
-                                futures::future::join_all(futures).await,
-                            );
-
-                            let mut access_points = wireless_device.access_points().await.unwrap();
-                            let futures = access_points.iter_mut().map(|ap| async {
-                                WifiConnection {
-                                    ssid: ap.id().await.unwrap().into(),
-                                    hw_address: ap.hw_address().await.unwrap(),
-                                    strength: ap.strength().await.unwrap(),
-                                    secure: ap.secure().await.unwrap(),
-                                }
-                            });
-                            let mut available_connections =
-                                futures::future::join_all(futures).await;
-
-                            let known_connections = ssids_of_known_connections
-                                .iter()
-                                .map(|ssid| {
-                                    match available_connections
-                                        .iter()
-                                        .find(|connection| *connection.ssid == *ssid)
-                                    {
-                                        Some(connection) => connection.clone(),
-                                        None => WifiConnection {
-                                            ssid: ssid.clone(),
-                                            hw_address: "".into(),
-                                            strength: 0,
-                                            secure: false,
-                                        },
-                                    }
-                                })
-                                .collect::<Vec<WifiConnection>>()
-                                .clone();
+                if device.metered()?.is_metered() {
+                    badges |= Badges::METERED;
+                }
 
-                            let known_connections = known_connections
-                                .clone()
-                                .into_iter()
-                                .filter(|connection| !connection.hw_address.is_empty())
-                                .collect::<Vec<WifiConnection>>();
+                if device.ip6_connectivity()? == NmConnectivityState::Full
+                    && device.ip4_connectivity()? != NmConnectivityState::Full
+                {
+                    badges |= Badges::IPV6_ONLY;
+                }
 
-                            available_connections.sort_by(|a, b| {
-                                let is_known = ssids_of_known_connections
-                                    .contains(&b.ssid)
-                                    .cmp(&ssids_of_known_connections.contains(&a.ssid));
-                                if is_known != Ordering::Equal {
-                                    return is_known;
-                                }
+                base = Some(device_base);
+                speed = device_speed;
+            }
+        }
 
-                                let is_stronger = b.strength.cmp(&a.strength);
+        let icon = match base {
+            Some(base) => {
+                if vpn {
+                    badges |= Badges::VPN;
+                }
+                Icon::Connected { base, badges }
+            }
+            None if vpn => Icon::Connected {
+                base: Base::Tun,
+                badges,
+            },
+            None => Icon::Unknown,
+        };
 
-                                if is_stronger != Ordering::Equal {
-                                    return is_stronger;
-                                }
+        Ok((icon, speed))
+    }
+}
 
-                                let is_secure = b.secure.cmp(&a.secure);
+fn activation_stage(state: DeviceState) -> Option<ActivationStage> {
+    match state {
+        DeviceState::Prepare => Some(ActivationStage::Prepare),
+        DeviceState::Config => Some(ActivationStage::Config),
+        DeviceState::NeedAuth => Some(ActivationStage::NeedAuth),
+        DeviceState::IpConfig => Some(ActivationStage::IpConfig),
+        DeviceState::IpCheck => Some(ActivationStage::IpCheck),
+        _ => None,
+    }
+}
 
-                                if is_secure != Ordering::Equal {
-                                    return is_secure;
-                                }
+/// Why the last attempt to connect `device` failed, if it did.
+fn failure(device: &Device) -> Option<String> {
+    device
+        .state_reason()
+        .ok()
+        .filter(|reason| reason.is_failure())
+        .map(|reason| reason.description().into())
+}
 
-                                a.ssid.to_lowercase().cmp(&b.ssid.to_lowercase())
-                            });
+pub fn tray_connectivity(connectivity: NmConnectivityState) -> Connectivity {
+    match connectivity {
+        NmConnectivityState::None => Connectivity::None,
+        NmConnectivityState::Portal => Connectivity::Portal,
+        NmConnectivityState::Loss => Connectivity::Limited,
+        NmConnectivityState::Full => Connectivity::Full,
+        NmConnectivityState::Unknown => Connectivity::Unknown,
+    }
+}
 
-                            let active_connnection = device.active_connection().await.unwrap();
-                            let active_connection_id = active_connnection.id().await.unwrap();
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::channel;
 
-                            let active_connection_index = known_connections
-                                .iter()
-                                .position(|connection| connection.ssid == active_connection_id)
-                                .unwrap_or_default();
+    use super::*;
+    use crate::network::mock::{
+        MockActiveConnection, MockBus, MockDevice, MockNetworkManager, MockWireless, path,
+    };
 
-                            tray_manager
-                                .update(TrayUpdate::Wireless(Some(WifiState {
-                                    on: true,
-                                    active_connection_index,
-                                    known_connections,
-                                    available_connections,
-                                })))
-                                .await;
-                        }
-                        DeviceState::Disconnected => {
-                            let on = self.network_manager.wifi_enabled().await.unwrap();
-                            tray_manager
-                                .update(TrayUpdate::Wireless(Some(WifiState {
-                                    on,
-                                    active_connection_index: 0,
-                                    known_connections: vec![],
-                                    available_connections: vec![],
-                                })))
-                                .await;
+    /// Keeps the last Wi-Fi section it was shown.
+    #[derive(Default)]
+    struct Recorder {
+        wifi: Option<WifiState>,
+    }
 
-                            if on {
-                                info!("Waiting for wireless device to activate...");
-                                let mut state_change_signal =
-                                    device.receive_state_changed_signal().await.unwrap();
+    impl TrayManager for Recorder {
+        async fn update(&mut self, update: TrayUpdate) {
+            if let TrayUpdate::Wireless(wifi) = update {
+                self.wifi = wifi;
+            }
+        }
 
-                                let mut attempts = 0;
-                                let max_attempts = 20;
+        async fn refresh(&mut self) {}
+    }
 
-                                loop {
-                                    attempts += 1;
+    /// Serves `device` as NetworkManager's only device and runs an update of the icon and the
+    /// Wi-Fi section.
+    async fn update_wifi(
+        bus: &MockBus,
+        network_manager: MockNetworkManager,
+        device_path: &str,
+        device: MockDevice,
+    ) -> Recorder {
+        bus.serve(device_path, device).await;
+        bus.serve(
+            device_path,
+            MockWireless {
+                access_points: vec![],
+            },
+        )
+        .await;
+        bus.serve(
+            "/org/freedesktop/NetworkManager",
+            MockNetworkManager {
+                devices: vec![path(device_path)],
+                ..network_manager
+            },
+        )
+        .await;
+
+        let network_manager = NetworkManager::new(bus.connection.clone()).await.unwrap();
+        let (event_tx, _event_rx) = channel(16);
+        let (action_tx, _action_rx) = channel(16);
+        let app = App::new(event_tx, action_tx, network_manager, Config::default());
+
+        let mut recorder = Recorder::default();
+        let flow = app
+            .update(&mut recorder, Subsystems::ICON | Subsystems::WIFI)
+            .await;
+        assert!(flow.is_continue());
+        recorder
+    }
 
-                                    if attempts > max_attempts {
-                                        break;
-                                    }
+    #[tokio::test]
+    async fn activation_stage_shows_while_connecting() {
+        const ACTIVE_CONNECTION_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/20";
+
+        let bus = MockBus::new().await;
+        bus.serve(
+            ACTIVE_CONNECTION_PATH,
+            MockActiveConnection { id: "Home".into() },
+        )
+        .await;
+        let recorder = update_wifi(
+            &bus,
+            MockNetworkManager {
+                // NmState::Connecting
+                state: 40,
+                ..Default::default()
+            },
+            "/org/freedesktop/NetworkManager/Devices/20",
+            MockDevice {
+                device_type: 2,
+                // DeviceState::IpConfig
+                state: 70,
+                state_reason: 0,
+                active_connection: path(ACTIVE_CONNECTION_PATH),
+            },
+        )
+        .await;
 
-                                    info!("Attempt {}/{}", attempts, max_attempts);
+        let activation = recorder.wifi.unwrap().activation.unwrap();
+        assert_eq!(activation.ssid, "Home");
+        assert_eq!(activation.stage, ActivationStage::IpConfig);
+    }
 
-                                    if let Ok(Some(state_changed)) = tokio::time::timeout(
-                                        Duration::from_secs(20),
-                                        state_change_signal.next(),
-                                    )
-                                    .await
-                                    {
-                                        let state = match state_changed.args() {
-                                            Ok(state) => DeviceState::from(state.new_state),
-                                            Err(_) => {
-                                                error!("Failed to get wireless device state");
-                                                break;
-                                            }
-                                        };
-
-                                        if matches!(state, DeviceState::Activated) {
-                                            info!("Wireless device activated!");
-                                            self.send_event(Event::Update).await;
-                                            break;
-                                        }
+    #[tokio::test]
+    async fn failure_shows_while_disconnected() {
+        let bus = MockBus::new().await;
+        let recorder = update_wifi(
+            &bus,
+            MockNetworkManager {
+                // NmState::Disconnected
+                state: 20,
+                ..Default::default()
+            },
+            "/org/freedesktop/NetworkManager/Devices/21",
+            MockDevice {
+                device_type: 2,
+                // DeviceState::Disconnected
+                state: 30,
+                // DeviceStateReason::NoSecrets
+                state_reason: 7,
+                active_connection: path("/"),
+            },
+        )
+        .await;
 
-                                        warn!("Wireless device not activated yet. Trying again...");
-                                    }
-                                }
-                            }
-                        }
-                        DeviceState::Unavailable => {
-                            // Often happens when the wifi is disabled or airplane mode is on
-                            info!("Wireless Device state: Unavailable");
-                            tray_manager
-                                .update(TrayUpdate::Wireless(Some(WifiState {
-                                    on: false,
-                                    active_connection_index: 0,
-                                    known_connections: vec![],
-                                    available_connections: vec![],
-                                })))
-                                .await;
-                        }
-                        _ => {}
-                    }
-                }
-                DeviceType::Ethernet => match device.state().await.unwrap() {
-                    DeviceState::Activated => {
-                        tray_manager
-                            .update(TrayUpdate::Wired(Some(WiredState { on: true })))
-                            .await;
-                    }
-                    DeviceState::Disconnected => {
-                        tray_manager
-                            .update(TrayUpdate::Wired(Some(WiredState { on: false })))
-                            .await;
-                    }
+        assert_eq!(
+            recorder.wifi.unwrap().failure.as_deref(),
+            Some("The password is missing or wrong")
+        );
+    }
 
-                    _ => {}
+    #[tokio::test]
+    async fn hardware_switch_shows_while_disconnected_or_asleep() {
+        // NmState::Disconnected with DeviceState::Unavailable, and NmState::Asleep with
+        // DeviceState::Unmanaged.
+        for (state, device_state, device_path) in [
+            (20, 20, "/org/freedesktop/NetworkManager/Devices/22"),
+            (10, 10, "/org/freedesktop/NetworkManager/Devices/23"),
+        ] {
+            let bus = MockBus::new().await;
+            let recorder = update_wifi(
+                &bus,
+                MockNetworkManager {
+                    state,
+                    wireless_hardware_enabled: false,
+                    ..Default::default()
                 },
-                DeviceType::WireGuard => {
-                    let wire_guard_connection = device.active_connection().await.unwrap();
-                    let wire_guard_connection_id = wire_guard_connection.id().await.unwrap();
-                    let state = match device.state().await {
-                        Ok(state) => state,
-                        Err(e) => {
-                            error!("Failed to get device state: {}", e);
-                            return ControlFlow::Break(());
-                        }
-                    };
-
-                    match state {
-                        DeviceState::Activated => {
-                            let vpn_connection = VPNConnection {
-                                name: wire_guard_connection_id.clone(),
-                                on: true,
-                            };
-                            vpn_connections.push(vpn_connection);
-                        }
-                        DeviceState::Disconnected | DeviceState::Unavailable => {
-                            let vpn_connection = VPNConnection {
-                                name: wire_guard_connection_id.clone(),
-                                on: false,
-                            };
-
-                            vpn_connections.push(vpn_connection);
-                        }
-                        _ => {}
-                    }
-                }
-                _ => {}
-            }
-        }
+                device_path,
+                MockDevice {
+                    device_type: 2,
+                    state: device_state,
+                    state_reason: 0,
+                    active_connection: path("/"),
+                },
+            )
+            .await;
 
-        if !vpn_connections.is_empty() {
-            tray_manager
-                .update(TrayUpdate::Vpn(Some(VPNState {
-                    connections: vpn_connections,
-                })))
-                .await;
+            let wifi = recorder.wifi.unwrap();
+            assert!(!wifi.on);
+            assert!(wifi.hardware_blocked);
         }
-
-        ControlFlow::Continue(())
     }
 }
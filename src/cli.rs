@@ -0,0 +1,78 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::{control::Command, logging::LoggingArgs, trays::OutputFormat};
+
+/// A simple network manager applet for Linux.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Write the state to stdout for a status bar instead of showing tray icons.
+    #[arg(long, value_name = "FORMAT", value_enum, conflicts_with = "no_tray")]
+    pub format: Option<OutputFormat>,
+
+    /// Show no tray icons, keeping only the notifications, connectivity checks and logging.
+    #[arg(long)]
+    pub no_tray: bool,
+
+    /// Directory for the lock and log files when XDG_RUNTIME_DIR isn't set.
+    #[arg(long, value_name = "DIR")]
+    pub fallback_runtime_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub logging: LoggingArgs,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Send a command to the running applet.
+    Ctl {
+        #[command(subcommand)]
+        command: Command,
+    },
+    /// Print the state of the network.
+    Status {
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Wi-Fi networks in range.
+    Wifi {
+        #[command(subcommand)]
+        command: ListCommand,
+    },
+    /// VPN connections.
+    Vpn {
+        #[command(subcommand)]
+        command: ListCommand,
+    },
+    /// Print the network devices.
+    Devices {
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Write a systemd user unit and an autostart entry that start the applet with the session.
+    InstallService {
+        /// Replace files that already exist.
+        #[arg(long)]
+        force: bool,
+    },
+    // The same commands as `ctl`, for a second launch to hand to the running applet.
+    #[command(flatten)]
+    Control(Command),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ListCommand {
+    /// List them.
+    List {
+        /// Print JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+    },
+}
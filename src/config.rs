@@ -0,0 +1,271 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use futures::StreamExt;
+use inotify::{Inotify, WatchMask};
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::{network::enums::DeviceType, notifications::Category};
+
+const CONFIG_DIR: &str = "networkless";
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// How long to collect events for before updating, in milliseconds.
+    pub update_window_ms: u64,
+    pub trays: TraysConfig,
+    pub wifi: WifiConfig,
+    pub devices: DevicesConfig,
+    pub icons: IconsConfig,
+    pub notifications: NotificationsConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            update_window_ms: 250,
+            trays: TraysConfig::default(),
+            wifi: WifiConfig::default(),
+            devices: DevicesConfig::default(),
+            icons: IconsConfig::default(),
+            notifications: NotificationsConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    pub fn update_window(&self) -> Duration {
+        Duration::from_millis(self.update_window_ms)
+    }
+}
+
+/// Which trays to show next to the network tray.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TraysConfig {
+    pub vpn: bool,
+    pub airplane_mode: bool,
+}
+
+impl Default for TraysConfig {
+    fn default() -> Self {
+        Self {
+            vpn: true,
+            airplane_mode: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WifiConfig {
+    /// How many available networks to list. All of them are listed when unset.
+    pub max_networks: Option<usize>,
+    /// Keys to sort available networks by, in order of precedence.
+    pub sort: Vec<SortKey>,
+    /// Minimum strength for each of the 25%, 50%, 75% and 100% signal icons.
+    pub strength_thresholds: [u8; 4],
+}
+
+impl Default for WifiConfig {
+    fn default() -> Self {
+        Self {
+            max_networks: None,
+            sort: vec![
+                SortKey::Known,
+                SortKey::Strength,
+                SortKey::Security,
+                SortKey::Name,
+            ],
+            strength_thresholds: [20, 40, 50, 80],
+        }
+    }
+}
+
+impl WifiConfig {
+    /// Maps a strength to one of the five signal icons, 0 being the weakest.
+    pub fn signal_level(&self, strength: u8) -> usize {
+        self.strength_thresholds
+            .iter()
+            .filter(|threshold| strength >= **threshold)
+            .count()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    /// Known networks first.
+    Known,
+    /// Stronger networks first.
+    Strength,
+    /// Secure networks first.
+    Security,
+    /// Alphabetically by SSID, ignoring case.
+    Name,
+}
+
+/// Devices to leave out of the menus.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DevicesConfig {
+    pub hidden_types: Vec<DeviceType>,
+    pub hidden_interfaces: Vec<String>,
+}
+
+impl DevicesConfig {
+    pub fn is_hidden(&self, device_type: DeviceType, interface: &str) -> bool {
+        self.hidden_types.contains(&device_type)
+            || self
+                .hidden_interfaces
+                .iter()
+                .any(|hidden| hidden == interface)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct IconsConfig {
+    pub source: IconSource,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IconSource {
+    /// The icons shipped with the applet.
+    #[default]
+    Embedded,
+    /// Icons from the desktop's icon theme, falling back to the embedded ones.
+    Theme,
+}
+
+/// Which events to show desktop notifications for.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotificationsConfig {
+    pub connection: bool,
+    pub vpn: bool,
+    pub activation_failed: bool,
+    pub connectivity: bool,
+    pub captive_portal: bool,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            connection: true,
+            vpn: true,
+            activation_failed: true,
+            connectivity: true,
+            captive_portal: true,
+        }
+    }
+}
+
+impl NotificationsConfig {
+    pub fn is_enabled(&self, category: Category) -> bool {
+        match category {
+            Category::Connection => self.connection,
+            Category::Vpn => self.vpn,
+            Category::ActivationFailed => self.activation_failed,
+            Category::Connectivity => self.connectivity,
+            Category::CaptivePortal => self.captive_portal,
+        }
+    }
+}
+
+impl Config {
+    /// Reads the config file, falling back to the defaults when there is none.
+    pub fn load() -> Result<Self> {
+        let Some(path) = config_file() else {
+            warn!("Neither XDG_CONFIG_HOME nor HOME is set. Using the default config");
+            return Ok(Self::default());
+        };
+
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => bail!("Failed to read {}: {}", path.display(), e),
+        };
+
+        toml::from_str(&contents).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+}
+
+pub fn config_home() -> Option<PathBuf> {
+    match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")),
+    }
+}
+
+pub fn config_dir() -> Option<PathBuf> {
+    config_home().map(|dir| dir.join(CONFIG_DIR))
+}
+
+fn config_file() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Calls `f` whenever the config file is written, replaced or removed.
+///
+/// Editors tend to save by renaming a temporary file over the original, so the directory is
+/// watched rather than the file itself. Its parent is watched as well in case the directory is
+/// created or removed while running.
+pub async fn listening_to_config_changes<F>(f: F) -> Result<()>
+where
+    F: AsyncFnOnce() -> () + Send + Copy,
+{
+    let Some(config_home) = config_home() else {
+        bail!("Neither XDG_CONFIG_HOME nor HOME is set");
+    };
+    let config_dir = config_home.join(CONFIG_DIR);
+
+    let inotify = Inotify::init()?;
+
+    let changes =
+        WatchMask::CREATE | WatchMask::DELETE | WatchMask::MOVED_TO | WatchMask::MOVED_FROM;
+
+    let config_home_watch = inotify.watches().add(&config_home, changes)?;
+
+    let config_dir_mask = changes | WatchMask::CLOSE_WRITE;
+    let mut config_dir_watch = match inotify.watches().add(&config_dir, config_dir_mask) {
+        Ok(watch) => Some(watch),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    info!("Watching {} for changes", config_dir.display());
+
+    let mut stream = inotify.into_event_stream([0; 1024])?;
+
+    while let Some(event) = stream.next().await {
+        let event = event?;
+
+        let Some(name) = event.name else {
+            continue;
+        };
+
+        if event.wd == config_home_watch && name == OsStr::new(CONFIG_DIR) {
+            config_dir_watch = match stream.watches().add(&config_dir, config_dir_mask) {
+                Ok(watch) => Some(watch),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            f().await;
+        } else if Some(&event.wd) == config_dir_watch.as_ref() && name == OsStr::new(CONFIG_FILE) {
+            f().await;
+        }
+    }
+
+    Ok(())
+}
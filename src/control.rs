@@ -0,0 +1,139 @@
+use anyhow::{Result, bail};
+use clap::Subcommand;
+use log::info;
+use zbus::{Connection, connection, fdo, interface};
+
+use crate::{
+    APP_ID,
+    app::{Action, App},
+    interfaces::networkless::NetworklessProxy,
+};
+
+const OBJECT_PATH: &str = "/com/collinslagat/applets/networkless";
+
+/// Lets scripts and keybindings drive the running applet. Every method queues the matching
+/// `Action` and returns without waiting for it to finish.
+struct Control {
+    app: App,
+}
+
+impl Control {
+    async fn send(&self, action: Action) -> fdo::Result<()> {
+        info!("Received {:?} over D-Bus", action);
+
+        self.app
+            .send_action(action)
+            .await
+            .map_err(|e| fdo::Error::Failed(e.to_string()))
+    }
+}
+
+#[interface(name = "com.collinslagat.applets.networkless")]
+impl Control {
+    async fn toggle_wifi(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleWifi).await
+    }
+
+    async fn toggle_wired(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleWired).await
+    }
+
+    async fn toggle_bluetooth(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleBluetooth).await
+    }
+
+    async fn toggle_wwan(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleWwan).await
+    }
+
+    async fn toggle_airplane_mode(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleAirplaneMode).await
+    }
+
+    async fn toggle_networking(&self) -> fdo::Result<()> {
+        self.send(Action::ToggleNetworking).await
+    }
+
+    #[zbus(name = "ToggleVPN")]
+    async fn toggle_vpn(&self, name: String) -> fdo::Result<()> {
+        self.send(Action::ToggleVPN(name)).await
+    }
+
+    async fn request_scan(&self) -> fdo::Result<()> {
+        self.send(Action::RequestScan).await
+    }
+
+    async fn connect_to(&self, ssid: String) -> fdo::Result<()> {
+        self.send(Action::ConnectTo(ssid)).await
+    }
+
+    async fn check_connectivity(&self) -> fdo::Result<()> {
+        self.send(Action::CheckConnectivity).await
+    }
+}
+
+/// Owns `APP_ID` on the session bus and serves the control interface for as long as the returned
+/// connection is kept around.
+pub async fn serve(app: App) -> Result<Connection> {
+    let connection = connection::Builder::session()?
+        .name(APP_ID)?
+        .serve_at(OBJECT_PATH, Control { app })?
+        .build()
+        .await?;
+
+    info!("Serving {} on the session bus", APP_ID);
+
+    Ok(connection)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Subcommand)]
+pub enum Command {
+    /// Turn Wi-Fi on or off.
+    ToggleWifi,
+    /// Connect or disconnect the wired connections.
+    ToggleWired,
+    /// Turn Bluetooth on or off.
+    ToggleBluetooth,
+    /// Turn mobile broadband on or off.
+    ToggleWwan,
+    /// Turn airplane mode on or off.
+    ToggleAirplaneMode,
+    /// Turn all networking on or off.
+    ToggleNetworking,
+    /// Connect or disconnect a VPN.
+    #[command(name = "toggle-vpn")]
+    ToggleVPN { name: String },
+    /// Scan for Wi-Fi networks.
+    RequestScan,
+    /// Connect to the strongest access point of a Wi-Fi network.
+    ConnectTo { ssid: String },
+    /// Check for internet access now.
+    CheckConnectivity,
+}
+
+impl Command {
+    /// Sends the command to the running applet.
+    pub async fn send(&self) -> Result<()> {
+        let connection = Connection::session().await?;
+        let proxy = NetworklessProxy::new(&connection).await?;
+
+        let result = match self {
+            Command::ToggleWifi => proxy.toggle_wifi().await,
+            Command::ToggleWired => proxy.toggle_wired().await,
+            Command::ToggleBluetooth => proxy.toggle_bluetooth().await,
+            Command::ToggleWwan => proxy.toggle_wwan().await,
+            Command::ToggleAirplaneMode => proxy.toggle_airplane_mode().await,
+            Command::ToggleNetworking => proxy.toggle_networking().await,
+            Command::ToggleVPN { name } => proxy.toggle_vpn(name).await,
+            Command::RequestScan => proxy.request_scan().await,
+            Command::ConnectTo { ssid } => proxy.connect_to(ssid).await,
+            Command::CheckConnectivity => proxy.check_connectivity().await,
+        };
+
+        match result.map_err(fdo::Error::from) {
+            Ok(()) => Ok(()),
+            Err(fdo::Error::ServiceUnknown(_)) => bail!("networkless-rs is not running"),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
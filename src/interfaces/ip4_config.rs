@@ -0,0 +1,69 @@
+//! # D-Bus interface proxy for: `org.freedesktop.NetworkManager.IP4Config`
+//!
+//! This code was generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/freedesktop/NetworkManager/IP4Config/3' from service 'org.freedesktop.NetworkManager' on system bus`.
+//!
+//! You may prefer to adapt it, instead of using it verbatim.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! This type implements the [D-Bus standard interfaces], (`org.freedesktop.DBus.*`) for which the
+//! following zbus API can be used:
+//!
+//! * [`zbus::fdo::PropertiesProxy`]
+//! * [`zbus::fdo::IntrospectableProxy`]
+//! * [`zbus::fdo::PeerProxy`]
+//!
+//! Consequently `zbus-xmlgen` did not generate code for the above interfaces.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+//! [D-Bus standard interfaces]: https://dbus.freedesktop.org/doc/dbus-specification.html#standard-interfaces,
+use zbus::proxy;
+#[proxy(
+    interface = "org.freedesktop.NetworkManager.IP4Config",
+    default_service = "org.freedesktop.NetworkManager"
+)]
+pub trait IP4Config {
+    /// AddressData property
+    #[zbus(property)]
+    fn address_data(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// DnsOptions property
+    #[zbus(property)]
+    fn dns_options(&self) -> zbus::Result<Vec<String>>;
+
+    /// DnsPriority property
+    #[zbus(property)]
+    fn dns_priority(&self) -> zbus::Result<i32>;
+
+    /// Domains property
+    #[zbus(property)]
+    fn domains(&self) -> zbus::Result<Vec<String>>;
+
+    /// Gateway property
+    #[zbus(property)]
+    fn gateway(&self) -> zbus::Result<String>;
+
+    /// NameserverData property
+    #[zbus(property)]
+    fn nameserver_data(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// RouteData property
+    #[zbus(property)]
+    fn route_data(
+        &self,
+    ) -> zbus::Result<Vec<std::collections::HashMap<String, zbus::zvariant::OwnedValue>>>;
+
+    /// Searches property
+    #[zbus(property)]
+    fn searches(&self) -> zbus::Result<Vec<String>>;
+
+    /// WinsServerData property
+    #[zbus(property)]
+    fn wins_server_data(&self) -> zbus::Result<Vec<String>>;
+}
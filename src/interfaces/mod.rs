@@ -2,6 +2,12 @@ pub mod access_point;
 pub mod active;
 pub mod device;
 pub mod devices;
+pub mod ip4_config;
+pub mod login_manager;
 pub mod network_manager;
+pub mod networkless;
+pub mod notifications;
+pub mod open_uri;
 pub mod settings;
 pub mod statistics;
+pub mod status_notifier_watcher;
@@ -0,0 +1,41 @@
+//! Proxy for the applet's own `com.collinslagat.applets.networkless` interface, served by the
+//! running applet in `control.rs` and used by `networkless-rs ctl`. Written by hand to match that
+//! interface rather than generated, so keep the two in step.
+use zbus::proxy;
+#[proxy(
+    interface = "com.collinslagat.applets.networkless",
+    default_service = "com.collinslagat.applets.networkless",
+    default_path = "/com/collinslagat/applets/networkless"
+)]
+pub trait Networkless {
+    /// CheckConnectivity method
+    fn check_connectivity(&self) -> zbus::Result<()>;
+
+    /// ConnectTo method
+    fn connect_to(&self, ssid: &str) -> zbus::Result<()>;
+
+    /// RequestScan method
+    fn request_scan(&self) -> zbus::Result<()>;
+
+    /// ToggleAirplaneMode method
+    fn toggle_airplane_mode(&self) -> zbus::Result<()>;
+
+    /// ToggleBluetooth method
+    fn toggle_bluetooth(&self) -> zbus::Result<()>;
+
+    /// ToggleNetworking method
+    fn toggle_networking(&self) -> zbus::Result<()>;
+
+    /// ToggleVPN method
+    #[zbus(name = "ToggleVPN")]
+    fn toggle_vpn(&self, name: &str) -> zbus::Result<()>;
+
+    /// ToggleWifi method
+    fn toggle_wifi(&self) -> zbus::Result<()>;
+
+    /// ToggleWired method
+    fn toggle_wired(&self) -> zbus::Result<()>;
+
+    /// ToggleWwan method
+    fn toggle_wwan(&self) -> zbus::Result<()>;
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use log::{info, warn};
+use tokio::process::Command;
+use zbus::Connection;
+
+use crate::interfaces::open_uri::OpenURIProxy;
+
+/// Opens `uri` in the user's default browser, through the desktop portal when it is available
+/// and with `xdg-open` otherwise.
+pub async fn open_uri(uri: &str) -> Result<()> {
+    match open_uri_with_portal(uri).await {
+        Ok(()) => {
+            info!("Opened {} through the desktop portal", uri);
+            return Ok(());
+        }
+        Err(e) => warn!("Failed to open {} through the desktop portal: {}", uri, e),
+    }
+
+    let status = Command::new("xdg-open").arg(uri).status().await?;
+    if !status.success() {
+        bail!("xdg-open exited with {}", status);
+    }
+
+    info!("Opened {} with xdg-open", uri);
+
+    Ok(())
+}
+
+async fn open_uri_with_portal(uri: &str) -> Result<()> {
+    let connection = Connection::session().await?;
+    let open_uri = OpenURIProxy::new(&connection).await?;
+    open_uri.open_uri("", uri, HashMap::new()).await?;
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Write},
+    os::unix::net::UnixDatagram,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use clap::{Args, ValueEnum};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use simplelog::{
+    ColorChoice, CombinedLogger, Config, SharedLogger, TermLogger, TerminalMode, WriteLogger,
+};
+
+const LOG_FILE: &str = "networkless.log";
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_IDENTIFIER: &str = "networkless-rs";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum LogTarget {
+    /// `--log-file`, or `networkless.log` in the runtime directory.
+    File,
+    Stderr,
+    /// The systemd journal, over its native protocol.
+    Journald,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct LoggingArgs {
+    /// How much to log: off, error, warn, info, debug or trace.
+    #[arg(long, value_name = "LEVEL", default_value = "info")]
+    pub log_level: LevelFilter,
+
+    /// Where to log. Can be given more than once.
+    #[arg(long = "log", value_name = "TARGET", value_enum, default_values_t = [LogTarget::File, LogTarget::Stderr])]
+    pub log_targets: Vec<LogTarget>,
+
+    /// Log file to write instead of `networkless.log` in the runtime directory.
+    #[arg(long, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+
+    /// Size at which the log file is rotated. 0 lets it grow without limit.
+    #[arg(long, value_name = "BYTES", default_value_t = 5 * 1024 * 1024)]
+    pub log_max_size: u64,
+
+    /// How many rotated log files to keep, the previous run's log included.
+    #[arg(long, value_name = "COUNT", default_value_t = 1)]
+    pub log_keep: usize,
+}
+
+pub fn init(args: &LoggingArgs, runtime_dir: &Path) -> Result<()> {
+    let mut targets = args.log_targets.clone();
+    targets.sort();
+    targets.dedup();
+
+    let mut loggers: Vec<Box<dyn SharedLogger>> = Vec::with_capacity(targets.len());
+
+    for target in targets {
+        match target {
+            LogTarget::File => {
+                let path = match &args.log_file {
+                    Some(path) => path.clone(),
+                    None => runtime_dir.join(LOG_FILE),
+                };
+                let file = RotatingFile::open(path, args.log_max_size, args.log_keep)?;
+                loggers.push(WriteLogger::new(args.log_level, Config::default(), file));
+            }
+            LogTarget::Stderr => {
+                // Never stdout, which carries the status bar output.
+                loggers.push(TermLogger::new(
+                    args.log_level,
+                    Config::default(),
+                    TerminalMode::Stderr,
+                    ColorChoice::Auto,
+                ));
+            }
+            LogTarget::Journald => {
+                loggers.push(JournaldLogger::new(args.log_level)?);
+            }
+        }
+    }
+
+    if let Err(e) = CombinedLogger::init(loggers) {
+        bail!("Failed to initialize logging: {}", e);
+    }
+
+    Ok(())
+}
+
+/// A log file that is moved aside once it grows past `max_size`, keeping `keep` old ones as
+/// `<path>.1`, `<path>.2` and so on, newest first.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+    /// Records are written in pieces, so rotation waits for the end of a line.
+    at_line_start: bool,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, keep: usize) -> Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+
+        // Every run starts a new file; the previous run's log becomes the first rotated one.
+        rotate_files(&path, keep)
+            .with_context(|| format!("Failed to rotate {}", path.display()))?;
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+
+        Ok(Self {
+            path,
+            file,
+            size: 0,
+            max_size,
+            keep,
+            at_line_start: true,
+        })
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        rotate_files(&self.path, self.keep)?;
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.max_size > 0 && self.size >= self.max_size && self.at_line_start {
+            self.rotate()?;
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.at_line_start = buf[written - 1] == b'\n';
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(format!(".{}", index));
+    PathBuf::from(rotated)
+}
+
+/// Shifts `path` and its rotated copies along by one, dropping the oldest. With `keep` at 0 there
+/// is nothing to shift and the file is simply truncated when reopened.
+fn rotate_files(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return Ok(());
+    }
+
+    for index in (1..keep).rev() {
+        rename_if_exists(&rotated_path(path, index), &rotated_path(path, index + 1))?;
+    }
+
+    rename_if_exists(path, &rotated_path(path, 1))
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Sends records straight to journald's socket, so they keep their priority and source location
+/// instead of arriving as plain stderr lines.
+struct JournaldLogger {
+    level: LevelFilter,
+    socket: UnixDatagram,
+}
+
+impl JournaldLogger {
+    fn new(level: LevelFilter) -> Result<Box<Self>> {
+        let socket = UnixDatagram::unbound()?;
+        socket
+            .connect(JOURNALD_SOCKET)
+            .with_context(|| format!("Failed to connect to {}", JOURNALD_SOCKET))?;
+
+        Ok(Box::new(Self { level, socket }))
+    }
+}
+
+impl Log for JournaldLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let priority = match record.level() {
+            Level::Error => "3",
+            Level::Warn => "4",
+            Level::Info => "6",
+            Level::Debug | Level::Trace => "7",
+        };
+
+        let mut payload = vec![];
+        push_field(&mut payload, "PRIORITY", priority);
+        push_field(&mut payload, "SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+        push_field(&mut payload, "MESSAGE", &record.args().to_string());
+        push_field(&mut payload, "TARGET", record.target());
+        if let Some(file) = record.file() {
+            push_field(&mut payload, "CODE_FILE", file);
+        }
+        if let Some(line) = record.line() {
+            push_field(&mut payload, "CODE_LINE", &line.to_string());
+        }
+
+        // Nowhere left to report a failure to.
+        let _ = self.socket.send(&payload);
+    }
+
+    fn flush(&self) {}
+}
+
+impl SharedLogger for JournaldLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        self
+    }
+}
+
+/// Appends a field in journald's native format. Values with newlines are sent length-prefixed.
+fn push_field(payload: &mut Vec<u8>, key: &str, value: &str) {
+    payload.extend_from_slice(key.as_bytes());
+
+    if value.contains('\n') {
+        payload.push(b'\n');
+        payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        payload.push(b'=');
+    }
+
+    payload.extend_from_slice(value.as_bytes());
+    payload.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty directory of the test's own under the system's temporary directory.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("networkless-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn rotates_at_max_size_once_a_line_ends() {
+        let dir = temp_dir("rotates_at_max_size");
+        let path = dir.join(LOG_FILE);
+        let mut file = RotatingFile::open(path.clone(), 10, 1).unwrap();
+
+        // Past the limit mid-line, so the end of the line still goes to the same file.
+        file.write_all(b"first line").unwrap();
+        file.write_all(b"\n").unwrap();
+        file.write_all(b"second\n").unwrap();
+
+        assert_eq!(read(&path), "second\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "first line\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn zero_max_size_never_rotates() {
+        let dir = temp_dir("zero_max_size");
+        let path = dir.join(LOG_FILE);
+        let mut file = RotatingFile::open(path.clone(), 0, 1).unwrap();
+
+        for _ in 0..100 {
+            file.write_all(b"line\n").unwrap();
+        }
+
+        assert_eq!(read(&path), "line\n".repeat(100));
+        assert!(!rotated_path(&path, 1).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn keeps_log_keep_rotated_files() {
+        let dir = temp_dir("keeps_log_keep");
+        let path = dir.join(LOG_FILE);
+        let mut file = RotatingFile::open(path.clone(), 1, 2).unwrap();
+
+        for line in ["1\n", "2\n", "3\n", "4\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        assert_eq!(read(&path), "4\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "3\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "2\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn previous_run_is_kept_unless_log_keep_is_zero() {
+        let dir = temp_dir("previous_run");
+        let path = dir.join(LOG_FILE);
+
+        fs::write(&path, "previous\n").unwrap();
+        RotatingFile::open(path.clone(), 0, 1).unwrap();
+        assert_eq!(read(&path), "");
+        assert_eq!(read(&rotated_path(&path, 1)), "previous\n");
+
+        fs::write(&path, "current\n").unwrap();
+        RotatingFile::open(path.clone(), 0, 0).unwrap();
+        assert_eq!(read(&path), "");
+        assert_eq!(read(&rotated_path(&path, 1)), "previous\n");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,49 +1,69 @@
+mod airplane_mode;
 mod app;
+mod cli;
+mod config;
+mod control;
 mod interfaces;
+mod launcher;
+mod logging;
 mod network;
+mod notifications;
+mod report;
+mod rfkill;
+mod scheduler;
+mod sleep;
+mod systemd;
 mod trays;
 
-use std::{fs::File, panic, path::Path};
+use std::{fs::File, os::unix::fs::DirBuilderExt, panic, path::PathBuf};
 
-use anyhow::{Result, bail};
+use anyhow::{Context, Result, bail};
 use app::{Action, App, Event};
+use clap::Parser;
+use cli::{Cli, CliCommand, ListCommand};
 use fs2::FileExt;
 use futures::StreamExt;
-use log::{LevelFilter, error, info, warn};
+use log::{error, info};
 use network::network_manager::NetworkManager;
+use report::{Query, Report};
 use signal_hook::consts::{SIGINT, SIGTERM};
 use signal_hook_tokio::Signals;
-use simplelog::{ColorChoice, CombinedLogger, Config, TermLogger, TerminalMode, WriteLogger};
 use tokio::{
     fs,
     sync::mpsc::{Sender, channel},
 };
-use trays::TrayManager;
-use zbus::{Connection, Proxy};
+use trays::{KsniTrayManager, NullTrayManager, StatusBar};
+use zbus::Connection;
 
 pub const APP_ID: &str = "com.collinslagat.applets.networkless";
 const LOCK_FILE: &str = "networkless.lock";
-const LOG_FILE: &str = "networkless.log";
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
-    if let Err(e) = setup_logging() {
-        eprintln!("Failed to initialize logging: {}", e);
-        std::process::exit(1);
-    }
-
-    panic::set_hook(Box::new(|info| {
-        error!("Unhandled panic: {}", info);
-    }));
-
-    let runtime_dir = match std::env::var("XDG_RUNTIME_DIR") {
-        Ok(dir) => dir,
-        Err(e) => {
-            bail!("XDG_RUNTIME_DIR not set: {}", e);
+    let cli = Cli::parse();
+
+    let command = match cli.command {
+        // `ctl` only talks to the running instance, so it leaves the lock and log files alone.
+        Some(CliCommand::Ctl { command }) => return command.send().await,
+        // Reports only read from NetworkManager, so they don't need the lock or the session bus.
+        Some(CliCommand::Status { json }) => return Report::new(Query::Status, json).print().await,
+        Some(CliCommand::Wifi {
+            command: ListCommand::List { json },
+        }) => return Report::new(Query::WifiList, json).print().await,
+        Some(CliCommand::Vpn {
+            command: ListCommand::List { json },
+        }) => return Report::new(Query::VpnList, json).print().await,
+        Some(CliCommand::Devices { json }) => {
+            return Report::new(Query::Devices, json).print().await;
         }
+        Some(CliCommand::InstallService { force }) => return systemd::install_service(force),
+        Some(CliCommand::Control(command)) => Some(command),
+        None => None,
     };
 
-    let lock_file_path = Path::new(&runtime_dir).join(LOCK_FILE);
+    let runtime_dir = runtime_dir(cli.fallback_runtime_dir)?;
+
+    let lock_file_path = runtime_dir.join(LOCK_FILE);
 
     let lock_file = match File::create(&lock_file_path) {
         Ok(file) => file,
@@ -53,12 +73,30 @@ async fn main() -> Result<()> {
     };
 
     if lock_file.try_lock_exclusive().is_err() {
-        bail!("Failed to acquire lock. Another instance is running.");
+        // Hand the command to the instance that holds the lock.
+        match command {
+            Some(command) => return command.send().await,
+            None => {
+                eprintln!("networkless-rs is already running");
+                return Ok(());
+            }
+        }
     }
 
-    info!("Lock acquired");
+    if command.is_some() {
+        bail!("networkless-rs is not running");
+    }
+
+    if let Err(e) = logging::init(&cli.logging, &runtime_dir) {
+        eprintln!("Failed to initialize logging: {:#}", e);
+        std::process::exit(1);
+    }
+
+    panic::set_hook(Box::new(|info| {
+        error!("Unhandled panic: {}", info);
+    }));
 
-    wait_for_session_bus_and_status_notifier().await?;
+    info!("Lock acquired");
 
     let (event_tx, event_rx) = channel::<Event>(32);
     let (action_tx, action_rx) = channel::<Action>(32);
@@ -72,16 +110,43 @@ async fn main() -> Result<()> {
     let connection = Connection::system().await?;
     let network_manager = NetworkManager::new(connection).await?;
 
-    let app = App::new(event_tx, action_tx, network_manager);
+    let config = config::Config::load().unwrap_or_else(|e| {
+        error!("Using the default config: {:#}", e);
+        config::Config::default()
+    });
 
-    let tray_manager = TrayManager::new(app.clone());
+    let app = App::new(event_tx, action_tx, network_manager, config);
+
+    // Kept alive until shutdown, since dropping it releases the name.
+    let _control_connection = match control::serve(app.clone()).await {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            error!("Failed to serve the control interface: {:#}", e);
+            None
+        }
+    };
 
     app.send_event(Event::Init).await;
 
-    app.run(event_rx, action_rx, tray_manager).await;
+    match cli.format {
+        Some(format) => {
+            let status_bar = StatusBar::new(app.clone(), format);
+            app.run(event_rx, action_rx, status_bar).await;
+        }
+        None if cli.no_tray => {
+            info!("Running without a tray");
+            app.run(event_rx, action_rx, NullTrayManager).await;
+        }
+        None => {
+            let tray_manager = KsniTrayManager::new(app.clone());
+            app.run(event_rx, action_rx, tray_manager).await;
+        }
+    }
 
     info!("Cleaning up");
 
+    systemd::notify("STOPPING=1");
+
     if let Err(e) = fs::remove_file(lock_file_path).await {
         error!("Failed to remove lock: {}", e);
     }
@@ -104,82 +169,26 @@ async fn handle_signals(mut signals: Signals, tx: Sender<Event>) {
     }
 }
 
-fn setup_logging() -> Result<()> {
-    let runtime_dir = match std::env::var("XDG_RUNTIME_DIR") {
-        Ok(dir) => dir,
-        Err(e) => {
-            bail!("XDG_RUNTIME_DIR not set: {}", e);
-        }
-    };
-
-    let log_file_path = Path::new(&runtime_dir).join(LOG_FILE);
+/// `$XDG_RUNTIME_DIR`, or `fallback`, or a private directory under the temporary directory.
+fn runtime_dir(fallback: Option<PathBuf>) -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_RUNTIME_DIR").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
 
-    let log_file = match File::create(log_file_path) {
-        Ok(file) => file,
-        Err(e) => {
-            bail!("Failed to create log file: {}", e);
+    let dir = match fallback {
+        Some(dir) => dir,
+        None => {
+            // SAFETY: getuid can't fail.
+            let uid = unsafe { libc::getuid() };
+            std::env::temp_dir().join(format!("networkless-{}", uid))
         }
     };
 
-    if let Err(e) = CombinedLogger::init(vec![
-        WriteLogger::new(LevelFilter::Info, Config::default(), log_file),
-        TermLogger::new(
-            LevelFilter::Info,
-            Config::default(),
-            TerminalMode::Mixed,
-            ColorChoice::Auto,
-        ),
-    ]) {
-        bail!("Failed to initialize logging: {}", e);
-    };
-
-    Ok(())
-}
-
-async fn wait_for_session_bus_and_status_notifier() -> Result<()> {
-    // Give up after 20 attempts spanning 100ms each for a total of 2s
-    let max_attempts = 20;
-
-    for attempt in 0..max_attempts {
-        match Connection::session().await {
-            Ok(connection) => {
-                let status_notifier = Proxy::new(
-                    &connection,
-                    "org.kde.StatusNotifierWatcher",
-                    "/StatusNotifierWatcher",
-                    "org.kde.StatusNotifierWatcher",
-                )
-                .await?;
-
-                match status_notifier
-                    .get_property::<bool>("IsStatusNotifierHostRegistered")
-                    .await
-                {
-                    Ok(_) => {
-                        info!(
-                            "Status notifier is available! (attempt {}/{})",
-                            attempt, max_attempts
-                        );
-                        return Ok(());
-                    }
-                    Err(e) => {
-                        warn!(
-                            "Failed to get IsStatusNotifierHostRegistered: {} (attempt {}/{})",
-                            e, attempt, max_attempts
-                        );
-                    }
-                };
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to connect to session bus: {} (attempt {}/{})",
-                    e, attempt, max_attempts
-                );
-            }
-        }
-
-        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-    }
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
 
-    Err(anyhow::anyhow!("Failed to connect to session bus"))
+    Ok(dir)
 }
@@ -1,70 +1,155 @@
-use zbus::{Result, zvariant::ObjectPath};
+use zbus::{Connection, Result, zvariant::ObjectPath};
 
-use crate::interfaces::access_point::AccessPointProxy;
+use super::{
+    enums::{ApFlags, ApSecurityFlags},
+    properties::CachedProperties,
+};
 
-use super::enums::{ApFlags, ApSecurityFlags};
+const INTERFACE: &str = "org.freedesktop.NetworkManager.AccessPoint";
 
 #[derive(Clone, Debug)]
 pub struct AccessPoint {
-    id: Option<String>,
-    access_point: AccessPointProxy<'static>,
+    path: ObjectPath<'static>,
+    properties: CachedProperties,
 }
 
 impl AccessPoint {
-    pub fn new(access_point: AccessPointProxy<'static>) -> Self {
-        Self {
-            id: None,
-            access_point,
-        }
+    pub async fn new(connection: &Connection, path: ObjectPath<'static>) -> Result<Self> {
+        let properties = CachedProperties::load(connection, &path, INTERFACE).await?;
+        Ok(Self { path, properties })
     }
 
-    pub async fn id(&mut self) -> Result<&str> {
-        if self.id.is_some() {
-            return Ok(self.id.as_ref().unwrap());
-        }
-        let ssid = self.access_point.ssid().await?;
-        let id = String::from_utf8_lossy(&ssid);
-        self.id = Some(id.to_string());
-        Ok(self.id.as_ref().unwrap())
+    pub fn id(&self) -> Result<String> {
+        let ssid: Vec<u8> = self.properties.get("Ssid")?;
+        Ok(String::from_utf8_lossy(&ssid).into_owned())
     }
 
-    pub async fn hw_address(&self) -> Result<String> {
-        self.access_point.hw_address().await
+    pub fn hw_address(&self) -> Result<String> {
+        self.properties.get("HwAddress")
     }
 
-    pub async fn strength(&self) -> Result<u8> {
-        self.access_point.strength().await
+    pub fn strength(&self) -> Result<u8> {
+        self.properties.get("Strength")
     }
 
-    pub async fn flags(&self) -> Result<ApFlags> {
-        self.access_point
-            .flags()
-            .await
+    pub fn flags(&self) -> Result<ApFlags> {
+        self.properties
+            .get("Flags")
             .map(ApFlags::from_bits_truncate)
     }
 
-    pub async fn rsn_flags(&self) -> Result<ApSecurityFlags> {
-        self.access_point
-            .rsn_flags()
-            .await
+    pub fn rsn_flags(&self) -> Result<ApSecurityFlags> {
+        self.properties
+            .get("RsnFlags")
             .map(ApSecurityFlags::from_bits_truncate)
     }
 
-    pub async fn wpa_flags(&self) -> Result<ApSecurityFlags> {
-        self.access_point
-            .wpa_flags()
-            .await
+    pub fn wpa_flags(&self) -> Result<ApSecurityFlags> {
+        self.properties
+            .get("WpaFlags")
             .map(ApSecurityFlags::from_bits_truncate)
     }
 
-    pub async fn secure(&self) -> Result<bool> {
-        let has_privacy_flag = self.flags().await?.contains(ApFlags::PRIVACY);
-        let has_some_level_of_security = !self.rsn_flags().await?.contains(ApSecurityFlags::NONE)
-            && !self.wpa_flags().await?.contains(ApSecurityFlags::NONE);
+    pub fn secure(&self) -> Result<bool> {
+        let has_privacy_flag = self.flags()?.contains(ApFlags::PRIVACY);
+        let has_some_level_of_security = !self.rsn_flags()?.contains(ApSecurityFlags::NONE)
+            && !self.wpa_flags()?.contains(ApSecurityFlags::NONE);
         Ok(has_privacy_flag && has_some_level_of_security)
     }
 
     pub fn path(&self) -> ObjectPath<'static> {
-        self.access_point.inner().path().clone()
+        self.path.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::proxy::CacheProperties;
+
+    use super::*;
+    use crate::{
+        interfaces::access_point::AccessPointProxy,
+        network::mock::{Call, MockAccessPoint, MockBus},
+    };
+
+    /// A busy scan.
+    const ACCESS_POINTS: u32 = 40;
+
+    async fn serve_access_points(bus: &MockBus, first: u32) -> Vec<ObjectPath<'static>> {
+        let mut paths = Vec::new();
+        for index in first..first + ACCESS_POINTS {
+            let path = format!("/org/freedesktop/NetworkManager/AccessPoint/{}", index);
+            bus.serve(
+                &path,
+                MockAccessPoint {
+                    ssid: format!("Network {}", index),
+                    strength: 50,
+                },
+            )
+            .await;
+            paths.push(ObjectPath::try_from(path).unwrap());
+        }
+        paths
+    }
+
+    fn count(calls: &[Call], member: &str) -> usize {
+        calls.iter().filter(|call| call.member == member).count()
+    }
+
+    /// What two updates of the network list cost before the properties were cached: a `Get` for
+    /// every property read, `secure()` alone taking three.
+    #[tokio::test]
+    async fn reading_properties_one_by_one() {
+        let mut bus = MockBus::new().await;
+        let paths = serve_access_points(&bus, 100).await;
+
+        for _ in 0..2 {
+            for path in &paths {
+                let ap = AccessPointProxy::builder(&bus.connection)
+                    .path(path.clone())
+                    .unwrap()
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await
+                    .unwrap();
+
+                ap.ssid().await.unwrap();
+                ap.hw_address().await.unwrap();
+                ap.strength().await.unwrap();
+                ap.flags().await.unwrap();
+                ap.rsn_flags().await.unwrap();
+                ap.wpa_flags().await.unwrap();
+            }
+        }
+
+        let calls = bus.take_calls().await;
+        assert_eq!(calls.len(), 480);
+        assert_eq!(count(&calls, "Get"), 480);
+    }
+
+    #[tokio::test]
+    async fn reading_cached_properties() {
+        let mut bus = MockBus::new().await;
+        let paths = serve_access_points(&bus, 200).await;
+
+        for _ in 0..2 {
+            for path in &paths {
+                let ap = AccessPoint::new(&bus.connection, path.clone())
+                    .await
+                    .unwrap();
+
+                ap.id().unwrap();
+                ap.hw_address().unwrap();
+                ap.strength().unwrap();
+                ap.secure().unwrap();
+            }
+        }
+
+        // One GetAll per access point, and one GetNameOwner to subscribe to its changes. The
+        // second update is served from the cache.
+        let calls = bus.take_calls().await;
+        assert_eq!(calls.len(), 80);
+        assert_eq!(count(&calls, "GetAll"), 40);
+        assert_eq!(count(&calls, "GetNameOwner"), 40);
     }
 }
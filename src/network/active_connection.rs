@@ -1,49 +1,91 @@
 use anyhow::Result as AnyResult;
 use futures::StreamExt;
-use log::{error, info};
+use log::{error, info, warn};
 use zbus::{
-    Result,
+    Connection, Result,
+    proxy::CacheProperties,
     zvariant::{ObjectPath, OwnedObjectPath},
 };
 
 use crate::{
-    interfaces::{active::ActiveProxy, device::DeviceProxy},
-    network::enums::ActiveConnectionState,
+    interfaces::{
+        active::ActiveProxy, ip4_config::IP4ConfigProxy, settings::connection::ConnectionProxy,
+    },
+    network::enums::{ActiveConnectionState, ActiveConnectionStateReason},
 };
 
-use super::{device::Device, enums::DeviceType};
+use super::{
+    device::Device, enums::DeviceType, ip4_config::Ip4Config, is_unset,
+    properties::CachedProperties, settings::ConnectionSetting,
+};
+
+const INTERFACE: &str = "org.freedesktop.NetworkManager.Connection.Active";
 
 #[derive(Debug, Clone)]
 pub struct ActiveConnection {
     active_connection: ActiveProxy<'static>,
+    properties: CachedProperties,
 }
 
 impl ActiveConnection {
-    pub fn new(active_connection: ActiveProxy<'static>) -> Self {
-        Self { active_connection }
+    pub async fn new(active_connection: ActiveProxy<'static>) -> Result<Self> {
+        let properties = CachedProperties::load(
+            active_connection.inner().connection(),
+            active_connection.inner().path(),
+            INTERFACE,
+        )
+        .await?;
+        Ok(Self {
+            active_connection,
+            properties,
+        })
     }
 
-    pub async fn id(&self) -> Result<String> {
-        self.active_connection.id().await
+    pub async fn from_connection_and_path(
+        connection: &Connection,
+        path: OwnedObjectPath,
+    ) -> Result<Self> {
+        let active_connection = ActiveProxy::builder(connection)
+            .path(path)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        Self::new(active_connection).await
     }
 
-    pub async fn state(&self) -> Result<ActiveConnectionState> {
-        self.active_connection
-            .state()
-            .await
+    pub fn id(&self) -> Result<String> {
+        self.properties.get("Id")
+    }
+
+    pub fn vpn(&self) -> Result<bool> {
+        self.properties.get("Vpn")
+    }
+
+    pub async fn settings(&self) -> Result<ConnectionSetting> {
+        let connection: OwnedObjectPath = self.properties.get("Connection")?;
+        let settings = ConnectionProxy::builder(self.active_connection.inner().connection())
+            .path(connection)?
+            .build()
+            .await?;
+        Ok(ConnectionSetting::new(settings))
+    }
+
+    pub fn state(&self) -> Result<ActiveConnectionState> {
+        self.properties
+            .get::<u32>("State")
             .map(ActiveConnectionState::from)
     }
 
-    pub async fn device_type(&self) -> Result<DeviceType> {
-        self.active_connection.type_().await.map(DeviceType::from)
+    pub fn device_type(&self) -> Result<DeviceType> {
+        self.properties.get::<String>("Type").map(DeviceType::from)
     }
 
     pub fn path(&self) -> ObjectPath<'static> {
         self.active_connection.inner().path().clone()
     }
 
-    pub async fn specific_object(&self) -> Result<OwnedObjectPath> {
-        let specific_object = match self.active_connection.specific_object().await {
+    pub fn specific_object(&self) -> Result<Option<OwnedObjectPath>> {
+        let specific_object: OwnedObjectPath = match self.properties.get("SpecificObject") {
             Ok(specific_object) => specific_object,
             Err(e) => {
                 error!("Failed to get SpecificObject: {}", e);
@@ -51,20 +93,41 @@ impl ActiveConnection {
             }
         };
 
-        Ok(specific_object)
+        if is_unset(&specific_object) {
+            return Ok(None);
+        }
+
+        Ok(Some(specific_object))
+    }
+
+    pub async fn ip4_config(&self) -> Result<Option<Ip4Config>> {
+        let ip4_config: OwnedObjectPath = self.properties.get("Ip4Config")?;
+        if is_unset(&ip4_config) {
+            return Ok(None);
+        }
+
+        let ip4_config = IP4ConfigProxy::builder(self.active_connection.inner().connection())
+            .path(ip4_config)?
+            .build()
+            .await?;
+        Ok(Some(Ip4Config::new(ip4_config)))
     }
 
     pub async fn devices(&self) -> Result<Vec<Device>> {
-        let devices = self.active_connection.devices().await?;
+        let devices: Vec<OwnedObjectPath> = self.properties.get("Devices")?;
 
         let mut out = Vec::with_capacity(devices.len());
 
         for device in devices {
-            let device = DeviceProxy::builder(self.active_connection.inner().connection())
-                .path(device)?
-                .build()
-                .await?;
-            out.push(Device::new(device));
+            match Device::from_connection_and_path(
+                self.active_connection.inner().connection(),
+                device.clone(),
+            )
+            .await
+            {
+                Ok(device) => out.push(device),
+                Err(e) => warn!("Skipping device {}: {}", device, e),
+            }
         }
 
         Ok(out)
@@ -72,7 +135,7 @@ impl ActiveConnection {
 
     pub async fn listening_to_state_changes<F>(&self, f: F) -> AnyResult<()>
     where
-        F: AsyncFnOnce(ActiveConnectionState) -> () + Send + Copy,
+        F: AsyncFnOnce(ActiveConnectionState, ActiveConnectionStateReason) -> () + Send + Copy,
     {
         let mut stream = self
             .active_connection
@@ -82,16 +145,17 @@ impl ActiveConnection {
         while let Some(state_changed) = stream.next().await {
             info!("Active Connection State changed");
 
-            let state = match state_changed.args() {
-                Ok(state) => state.state().to_owned(),
+            let (state, reason) = match state_changed.args() {
+                Ok(args) => (args.state().to_owned(), args.reason().to_owned()),
                 Err(e) => {
                     anyhow::bail!("Failed to get StateChanged arguments: {e}");
                 }
             };
 
             let state = ActiveConnectionState::from(state);
+            let reason = ActiveConnectionStateReason::from(reason);
 
-            f(state).await;
+            f(state, reason).await;
         }
         Ok(())
     }
@@ -1,65 +1,105 @@
 use zbus::{
     Connection, Result,
+    proxy::CacheProperties,
     zvariant::{ObjectPath, OwnedObjectPath},
 };
 
 use crate::interfaces::{
-    active::ActiveProxy,
     device::{DeviceProxy, StateChangedStream},
-    devices::wireless::WirelessProxy,
+    devices::{wired::WiredProxy, wireless::WirelessProxy},
     settings::connection::ConnectionProxy,
 };
 
 use super::{
     active_connection::ActiveConnection,
-    devices::{SpecificDevice, Wireless},
-    enums::{DeviceState, DeviceType},
+    devices::{SpecificDevice, Wired, Wireless},
+    enums::{DeviceState, DeviceStateReason, DeviceType, NmConnectivityState, NmMetered},
+    is_unset,
+    properties::CachedProperties,
     settings::ConnectionSetting,
 };
 
+const INTERFACE: &str = "org.freedesktop.NetworkManager.Device";
+
 #[derive(Debug, Clone)]
 pub struct Device {
     device: DeviceProxy<'static>,
+    properties: CachedProperties,
 }
 
 impl Device {
-    pub fn new(device: DeviceProxy<'static>) -> Self {
-        Self { device }
+    pub async fn new(device: DeviceProxy<'static>) -> Result<Self> {
+        let properties = CachedProperties::load(
+            device.inner().connection(),
+            device.inner().path(),
+            INTERFACE,
+        )
+        .await?;
+        Ok(Self { device, properties })
     }
 
     pub async fn receive_state_changed_signal(&self) -> Result<StateChangedStream> {
         self.device.receive_state_changed_signal().await
     }
 
-    pub async fn state(&self) -> Result<DeviceState> {
-        self.device.state().await.map(DeviceState::from)
+    pub fn state(&self) -> Result<DeviceState> {
+        self.properties.get::<u32>("State").map(DeviceState::from)
+    }
+
+    /// Why the device entered its current state.
+    pub fn state_reason(&self) -> Result<DeviceStateReason> {
+        self.properties
+            .get::<(u32, u32)>("StateReason")
+            .map(|(_, reason)| DeviceStateReason::from(reason))
+    }
+
+    pub fn device_type(&self) -> Result<DeviceType> {
+        self.properties
+            .get::<u32>("DeviceType")
+            .map(DeviceType::from)
+    }
+
+    pub fn metered(&self) -> Result<NmMetered> {
+        self.properties.get::<u32>("Metered").map(NmMetered::from)
+    }
+
+    pub fn ip4_connectivity(&self) -> Result<NmConnectivityState> {
+        self.properties
+            .get::<u32>("Ip4Connectivity")
+            .map(NmConnectivityState::from)
+    }
+
+    pub fn ip6_connectivity(&self) -> Result<NmConnectivityState> {
+        self.properties
+            .get::<u32>("Ip6Connectivity")
+            .map(NmConnectivityState::from)
     }
 
-    pub async fn device_type(&self) -> Result<DeviceType> {
-        self.device.device_type().await.map(DeviceType::from)
+    pub fn interface(&self) -> Result<String> {
+        self.properties.get("Interface")
     }
 
     pub fn path(&self) -> ObjectPath<'static> {
         self.device.inner().path().clone()
     }
 
-    pub async fn active_connection(&self) -> Result<ActiveConnection> {
-        // BUG: It's possible for self.device.connection() to return an ObjectPath("/") which means that the
-        // active connection is not set. This will cause panics when you try to acess properties
-        // and call methods on it. Until this is fixed, we need to check if the active connection
-        // doesn't throw an error on any of the methods.
-        // In the future, this will probably return an Option<ActiveConnection> instead of
-        // a Result<ActiveConnection> and handle the error in the caller.
-        let active_connection = self.device.active_connection().await?;
-        let active_connection = ActiveProxy::builder(self.device.inner().connection())
-            .path(active_connection)?
-            .build()
-            .await?;
-        Ok(ActiveConnection::new(active_connection))
+    pub async fn active_connection(&self) -> Result<Option<ActiveConnection>> {
+        let active_connection: OwnedObjectPath = self.properties.get("ActiveConnection")?;
+        if is_unset(&active_connection) {
+            return Ok(None);
+        }
+
+        let active_connection = ActiveConnection::from_connection_and_path(
+            self.device.inner().connection(),
+            active_connection,
+        )
+        .await?;
+        Ok(Some(active_connection))
     }
 
     pub async fn available_connections(&self) -> Result<Vec<ConnectionSetting>> {
-        let configured_connections = self.device.available_connections().await.unwrap();
+        let configured_connections: Vec<OwnedObjectPath> =
+            self.properties.get("AvailableConnections")?;
 
         let mut out = Vec::with_capacity(configured_connections.len());
 
@@ -75,7 +115,7 @@ impl Device {
     }
 
     pub async fn to_specific_device(&self) -> Option<SpecificDevice> {
-        let device_type = match self.device_type().await {
+        let device_type = match self.device_type() {
             Ok(device_type) => device_type,
             Err(_) => return None,
         };
@@ -87,25 +127,40 @@ impl Device {
                 let wireless_device = WirelessProxy::builder(connection)
                     .path(path)
                     .unwrap()
+                    .cache_properties(CacheProperties::No)
                     .build()
                     .await
                     .unwrap();
-                let device = Wireless::new(wireless_device).await;
+                let device = Wireless::new(wireless_device).await.ok()?;
                 Some(SpecificDevice::Wireless(device))
             }
-            DeviceType::Ethernet => Some(SpecificDevice::Wired(())),
+            DeviceType::Ethernet => {
+                let connection = self.device.inner().connection();
+                let path = self.device.inner().path().clone();
+                let wired_device = WiredProxy::builder(connection)
+                    .path(path)
+                    .ok()?
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await
+                    .ok()?;
+                let device = Wired::new(wired_device).await.ok()?;
+                Some(SpecificDevice::Wired(device))
+            }
             DeviceType::WireGuard => Some(SpecificDevice::WireGuard(())),
             _ => None,
         }
     }
 
-    pub async fn from_connection_and_path(connection: &Connection, path: OwnedObjectPath) -> Self {
+    pub async fn from_connection_and_path(
+        connection: &Connection,
+        path: OwnedObjectPath,
+    ) -> Result<Self> {
         let device = DeviceProxy::builder(connection)
-            .path(path)
-            .unwrap()
+            .path(path)?
+            .cache_properties(CacheProperties::No)
             .build()
-            .await
-            .unwrap();
-        Self::new(device)
+            .await?;
+        Self::new(device).await
     }
 }
@@ -2,45 +2,69 @@ use std::collections::HashMap;
 
 use anyhow::Result as AnyResult;
 use futures::StreamExt;
-use log::info;
-use zbus::{Result, zvariant::Value};
+use log::{info, warn};
+use zbus::{
+    Result,
+    zvariant::{OwnedObjectPath, Value},
+};
 
-use crate::interfaces::{access_point::AccessPointProxy, devices::wireless::WirelessProxy};
+use crate::interfaces::devices::{wired::WiredProxy, wireless::WirelessProxy};
 
-use super::access_point::AccessPoint;
+use super::{access_point::AccessPoint, is_unset, properties::CachedProperties};
+
+const WIRELESS_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wireless";
+const WIRED_INTERFACE: &str = "org.freedesktop.NetworkManager.Device.Wired";
 
 #[derive(Debug, Clone)]
 pub struct Wireless {
     wireless_device: WirelessProxy<'static>,
+    properties: CachedProperties,
 }
 
 impl Wireless {
-    pub async fn new(wireless_device: WirelessProxy<'static>) -> Self {
-        Self { wireless_device }
+    pub async fn new(wireless_device: WirelessProxy<'static>) -> Result<Self> {
+        let properties = CachedProperties::load(
+            wireless_device.inner().connection(),
+            wireless_device.inner().path(),
+            WIRELESS_INTERFACE,
+        )
+        .await?;
+        Ok(Self {
+            wireless_device,
+            properties,
+        })
     }
 
-    pub async fn active_access_point(&self) -> Result<AccessPoint> {
-        let ap = self.wireless_device.active_access_point().await?;
-        let ap = AccessPointProxy::builder(self.wireless_device.inner().connection())
-            .path(ap)?
-            .build()
-            .await?;
-        Ok(AccessPoint::new(ap))
+    pub async fn active_access_point(&self) -> Result<Option<AccessPoint>> {
+        let ap: OwnedObjectPath = self.properties.get("ActiveAccessPoint")?;
+        if is_unset(&ap) {
+            return Ok(None);
+        }
+
+        let ap = AccessPoint::new(self.wireless_device.inner().connection(), ap.into()).await?;
+        Ok(Some(ap))
     }
 
+    /// Access points that go out of range while they are being loaded are left out.
     pub async fn access_points(&self) -> Result<Vec<AccessPoint>> {
-        let aps = self.wireless_device.access_points().await?;
+        let aps: Vec<OwnedObjectPath> = self.properties.get("AccessPoints")?;
         let mut out = Vec::with_capacity(aps.len());
         for ap in aps {
-            let ap = AccessPointProxy::builder(self.wireless_device.inner().connection())
-                .path(ap)?
-                .build()
-                .await?;
-            out.push(AccessPoint::new(ap));
+            match AccessPoint::new(self.wireless_device.inner().connection(), ap.clone().into())
+                .await
+            {
+                Ok(ap) => out.push(ap),
+                Err(e) => warn!("Skipping access point {}: {}", ap.as_str(), e),
+            }
         }
         Ok(out)
     }
 
+    /// Bitrate of the link in Kb/s.
+    pub fn bitrate(&self) -> Result<u32> {
+        self.properties.get("Bitrate")
+    }
+
     pub async fn request_scan(&self, opts: HashMap<&str, &Value<'static>>) -> Result<()> {
         self.wireless_device.request_scan(opts).await
     }
@@ -61,13 +85,18 @@ impl Wireless {
                 }
             };
 
-            let access_point_proxy =
-                AccessPointProxy::builder(self.wireless_device.inner().connection())
-                    .path(&access_point_path)?
-                    .build()
-                    .await?;
-
-            let access_point = AccessPoint::new(access_point_proxy);
+            let access_point = match AccessPoint::new(
+                self.wireless_device.inner().connection(),
+                access_point_path.clone(),
+            )
+            .await
+            {
+                Ok(access_point) => access_point,
+                Err(e) => {
+                    warn!("Skipping access point {}: {}", access_point_path, e);
+                    continue;
+                }
+            };
 
             f(access_point).await;
         }
@@ -76,7 +105,7 @@ impl Wireless {
 
     pub async fn listening_to_access_point_removed<F>(&self, f: F) -> AnyResult<()>
     where
-        F: AsyncFnOnce(AccessPoint) -> () + Send + Copy,
+        F: AsyncFnOnce(OwnedObjectPath) -> () + Send + Copy,
     {
         let mut stream = self.wireless_device.receive_access_point_removed().await?;
 
@@ -90,22 +119,99 @@ impl Wireless {
                 }
             };
 
-            let access_point_proxy =
-                AccessPointProxy::builder(self.wireless_device.inner().connection())
-                    .path(&access_point_path)?
-                    .build()
-                    .await?;
-
-            let access_point = AccessPoint::new(access_point_proxy);
-
-            f(access_point).await;
+            // The access point is already gone, so there is nothing left to load.
+            f(access_point_path.into()).await;
         }
         Ok(())
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct Wired {
+    _wired_device: WiredProxy<'static>,
+    properties: CachedProperties,
+}
+
+impl Wired {
+    pub async fn new(wired_device: WiredProxy<'static>) -> Result<Self> {
+        let properties = CachedProperties::load(
+            wired_device.inner().connection(),
+            wired_device.inner().path(),
+            WIRED_INTERFACE,
+        )
+        .await?;
+        Ok(Self {
+            _wired_device: wired_device,
+            properties,
+        })
+    }
+
+    /// Speed of the link in Mb/s.
+    pub fn speed(&self) -> Result<u32> {
+        self.properties.get("Speed")
+    }
+}
+
 pub enum SpecificDevice {
     Wireless(Wireless),
-    Wired(()),
+    Wired(Wired),
     WireGuard(()),
 }
+
+#[cfg(test)]
+mod tests {
+    use zbus::proxy::CacheProperties;
+
+    use super::*;
+    use crate::network::mock::{MockAccessPoint, MockBus, MockWireless};
+
+    async fn wireless(bus: &MockBus, path: &str, access_points: &[&str]) -> Wireless {
+        bus.serve(
+            path,
+            MockWireless {
+                access_points: access_points
+                    .iter()
+                    .map(|path| OwnedObjectPath::try_from(*path).unwrap())
+                    .collect(),
+            },
+        )
+        .await;
+
+        let proxy = WirelessProxy::builder(&bus.connection)
+            .path(path.to_owned())
+            .unwrap()
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+            .unwrap();
+        Wireless::new(proxy).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn access_points_skip_the_ones_that_went_away() {
+        let bus = MockBus::new().await;
+        bus.serve(
+            "/org/freedesktop/NetworkManager/AccessPoint/10",
+            MockAccessPoint {
+                ssid: "Home".into(),
+                strength: 80,
+            },
+        )
+        .await;
+        // AccessPoint/11 is listed by the device but no longer on the bus.
+        let wireless = wireless(
+            &bus,
+            "/org/freedesktop/NetworkManager/Devices/10",
+            &[
+                "/org/freedesktop/NetworkManager/AccessPoint/10",
+                "/org/freedesktop/NetworkManager/AccessPoint/11",
+            ],
+        )
+        .await;
+
+        let access_points = wireless.access_points().await.unwrap();
+
+        assert_eq!(access_points.len(), 1);
+        assert_eq!(access_points[0].id().unwrap(), "Home");
+    }
+}
@@ -1,6 +1,7 @@
 // From: https://github.com/pop-os/dbus-settings-bindings/blob/3b86984332be2c930a3536ab714b843c851fa8ca/networkmanager/src/interface/enums.rs
 
 use bitflags::bitflags;
+use serde::Deserialize;
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum NmState {
@@ -53,6 +54,35 @@ impl From<u32> for NmConnectivityState {
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NmMetered {
+    Yes,
+    No,
+    GuessYes,
+    GuessNo,
+    #[default]
+    Unknown,
+}
+
+impl From<u32> for NmMetered {
+    fn from(metered: u32) -> NmMetered {
+        match metered {
+            1 => NmMetered::Yes,
+            2 => NmMetered::No,
+            3 => NmMetered::GuessYes,
+            4 => NmMetered::GuessNo,
+            _ => NmMetered::Unknown,
+        }
+    }
+}
+
+impl NmMetered {
+    pub fn is_metered(&self) -> bool {
+        matches!(self, NmMetered::Yes | NmMetered::GuessYes)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum DeviceType {
     Ethernet,
     Wifi,
@@ -136,6 +166,292 @@ impl From<u32> for DeviceState {
     }
 }
 
+impl DeviceState {
+    /// Whether the device is between being disconnected and activated.
+    pub fn is_activating(&self) -> bool {
+        matches!(
+            self,
+            DeviceState::Prepare
+                | DeviceState::Config
+                | DeviceState::NeedAuth
+                | DeviceState::IpConfig
+                | DeviceState::IpCheck
+                | DeviceState::Secondaries
+        )
+    }
+}
+
+/// Why a device changed state, as reported along with its `StateChanged` signal and its
+/// `StateReason` property.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStateReason {
+    None,
+    #[default]
+    Unknown,
+    NowManaged,
+    NowUnmanaged,
+    ConfigFailed,
+    IpConfigUnavailable,
+    IpConfigExpired,
+    NoSecrets,
+    SupplicantDisconnect,
+    SupplicantConfigFailed,
+    SupplicantFailed,
+    SupplicantTimeout,
+    PppStartFailed,
+    PppDisconnect,
+    PppFailed,
+    DhcpStartFailed,
+    DhcpError,
+    DhcpFailed,
+    SharedStartFailed,
+    SharedFailed,
+    AutoIpStartFailed,
+    AutoIpError,
+    AutoIpFailed,
+    ModemBusy,
+    ModemNoDialTone,
+    ModemNoCarrier,
+    ModemDialTimeout,
+    ModemDialFailed,
+    ModemInitFailed,
+    GsmApnFailed,
+    GsmRegistrationNotSearching,
+    GsmRegistrationDenied,
+    GsmRegistrationTimeout,
+    GsmRegistrationFailed,
+    GsmPinCheckFailed,
+    FirmwareMissing,
+    Removed,
+    Sleeping,
+    ConnectionRemoved,
+    UserRequested,
+    Carrier,
+    ConnectionAssumed,
+    SupplicantAvailable,
+    ModemNotFound,
+    BluetoothFailed,
+    GsmSimNotInserted,
+    GsmSimPinRequired,
+    GsmSimPukRequired,
+    GsmSimWrong,
+    InfinibandMode,
+    DependencyFailed,
+    Br2684Failed,
+    ModemManagerUnavailable,
+    SsidNotFound,
+    SecondaryConnectionFailed,
+    DcbFcoeFailed,
+    TeamdControlFailed,
+    ModemFailed,
+    ModemAvailable,
+    SimPinIncorrect,
+    NewActivation,
+    ParentChanged,
+    ParentManagedChanged,
+    OvsdbFailed,
+    IpAddressDuplicate,
+    IpMethodUnsupported,
+    SriovConfigurationFailed,
+    PeerNotFound,
+    DeviceHandlerFailed,
+}
+
+impl From<u32> for DeviceStateReason {
+    fn from(reason: u32) -> Self {
+        match reason {
+            0 => DeviceStateReason::None,
+            2 => DeviceStateReason::NowManaged,
+            3 => DeviceStateReason::NowUnmanaged,
+            4 => DeviceStateReason::ConfigFailed,
+            5 => DeviceStateReason::IpConfigUnavailable,
+            6 => DeviceStateReason::IpConfigExpired,
+            7 => DeviceStateReason::NoSecrets,
+            8 => DeviceStateReason::SupplicantDisconnect,
+            9 => DeviceStateReason::SupplicantConfigFailed,
+            10 => DeviceStateReason::SupplicantFailed,
+            11 => DeviceStateReason::SupplicantTimeout,
+            12 => DeviceStateReason::PppStartFailed,
+            13 => DeviceStateReason::PppDisconnect,
+            14 => DeviceStateReason::PppFailed,
+            15 => DeviceStateReason::DhcpStartFailed,
+            16 => DeviceStateReason::DhcpError,
+            17 => DeviceStateReason::DhcpFailed,
+            18 => DeviceStateReason::SharedStartFailed,
+            19 => DeviceStateReason::SharedFailed,
+            20 => DeviceStateReason::AutoIpStartFailed,
+            21 => DeviceStateReason::AutoIpError,
+            22 => DeviceStateReason::AutoIpFailed,
+            23 => DeviceStateReason::ModemBusy,
+            24 => DeviceStateReason::ModemNoDialTone,
+            25 => DeviceStateReason::ModemNoCarrier,
+            26 => DeviceStateReason::ModemDialTimeout,
+            27 => DeviceStateReason::ModemDialFailed,
+            28 => DeviceStateReason::ModemInitFailed,
+            29 => DeviceStateReason::GsmApnFailed,
+            30 => DeviceStateReason::GsmRegistrationNotSearching,
+            31 => DeviceStateReason::GsmRegistrationDenied,
+            32 => DeviceStateReason::GsmRegistrationTimeout,
+            33 => DeviceStateReason::GsmRegistrationFailed,
+            34 => DeviceStateReason::GsmPinCheckFailed,
+            35 => DeviceStateReason::FirmwareMissing,
+            36 => DeviceStateReason::Removed,
+            37 => DeviceStateReason::Sleeping,
+            38 => DeviceStateReason::ConnectionRemoved,
+            39 => DeviceStateReason::UserRequested,
+            40 => DeviceStateReason::Carrier,
+            41 => DeviceStateReason::ConnectionAssumed,
+            42 => DeviceStateReason::SupplicantAvailable,
+            43 => DeviceStateReason::ModemNotFound,
+            44 => DeviceStateReason::BluetoothFailed,
+            45 => DeviceStateReason::GsmSimNotInserted,
+            46 => DeviceStateReason::GsmSimPinRequired,
+            47 => DeviceStateReason::GsmSimPukRequired,
+            48 => DeviceStateReason::GsmSimWrong,
+            49 => DeviceStateReason::InfinibandMode,
+            50 => DeviceStateReason::DependencyFailed,
+            51 => DeviceStateReason::Br2684Failed,
+            52 => DeviceStateReason::ModemManagerUnavailable,
+            53 => DeviceStateReason::SsidNotFound,
+            54 => DeviceStateReason::SecondaryConnectionFailed,
+            55 => DeviceStateReason::DcbFcoeFailed,
+            56 => DeviceStateReason::TeamdControlFailed,
+            57 => DeviceStateReason::ModemFailed,
+            58 => DeviceStateReason::ModemAvailable,
+            59 => DeviceStateReason::SimPinIncorrect,
+            60 => DeviceStateReason::NewActivation,
+            61 => DeviceStateReason::ParentChanged,
+            62 => DeviceStateReason::ParentManagedChanged,
+            63 => DeviceStateReason::OvsdbFailed,
+            64 => DeviceStateReason::IpAddressDuplicate,
+            65 => DeviceStateReason::IpMethodUnsupported,
+            66 => DeviceStateReason::SriovConfigurationFailed,
+            67 => DeviceStateReason::PeerNotFound,
+            68 => DeviceStateReason::DeviceHandlerFailed,
+            _ => DeviceStateReason::Unknown,
+        }
+    }
+}
+
+impl DeviceStateReason {
+    /// A short explanation that can be shown to the user.
+    pub fn description(&self) -> &'static str {
+        match self {
+            DeviceStateReason::None => "No reason given",
+            DeviceStateReason::Unknown => "Unknown error",
+            DeviceStateReason::NowManaged => "The device is now managed",
+            DeviceStateReason::NowUnmanaged => "The device is no longer managed",
+            DeviceStateReason::ConfigFailed => "The device could not be configured",
+            DeviceStateReason::IpConfigUnavailable => "No IP address could be obtained",
+            DeviceStateReason::IpConfigExpired => "The IP address lease expired",
+            DeviceStateReason::NoSecrets => "The password is missing or wrong",
+            DeviceStateReason::SupplicantDisconnect => {
+                "The network disconnected during authentication"
+            }
+            DeviceStateReason::SupplicantConfigFailed => {
+                "The Wi-Fi security settings could not be applied"
+            }
+            DeviceStateReason::SupplicantFailed => "Wi-Fi authentication failed",
+            DeviceStateReason::SupplicantTimeout => "Authentication timed out",
+            DeviceStateReason::PppStartFailed => "The PPP service could not be started",
+            DeviceStateReason::PppDisconnect => "The PPP service disconnected",
+            DeviceStateReason::PppFailed => "The PPP service failed",
+            DeviceStateReason::DhcpStartFailed => "DHCP could not be started",
+            DeviceStateReason::DhcpError => "DHCP failed",
+            DeviceStateReason::DhcpFailed => "DHCP timed out",
+            DeviceStateReason::SharedStartFailed => {
+                "The connection sharing service could not be started"
+            }
+            DeviceStateReason::SharedFailed => "The connection sharing service failed",
+            DeviceStateReason::AutoIpStartFailed => "Link-local addressing could not be started",
+            DeviceStateReason::AutoIpError => "Link-local addressing failed",
+            DeviceStateReason::AutoIpFailed => "No link-local address could be obtained",
+            DeviceStateReason::ModemBusy => "The line is busy",
+            DeviceStateReason::ModemNoDialTone => "There is no dial tone",
+            DeviceStateReason::ModemNoCarrier => "The modem has no carrier",
+            DeviceStateReason::ModemDialTimeout => "Dialing timed out",
+            DeviceStateReason::ModemDialFailed => "Dialing failed",
+            DeviceStateReason::ModemInitFailed => "The modem could not be initialized",
+            DeviceStateReason::GsmApnFailed => "The mobile access point could not be selected",
+            DeviceStateReason::GsmRegistrationNotSearching => {
+                "The modem is not searching for a mobile network"
+            }
+            DeviceStateReason::GsmRegistrationDenied => {
+                "Registration with the mobile network was denied"
+            }
+            DeviceStateReason::GsmRegistrationTimeout => {
+                "Registration with the mobile network timed out"
+            }
+            DeviceStateReason::GsmRegistrationFailed => {
+                "Registration with the mobile network failed"
+            }
+            DeviceStateReason::GsmPinCheckFailed => "The SIM PIN check failed",
+            DeviceStateReason::FirmwareMissing => "The device firmware is missing",
+            DeviceStateReason::Removed => "The device was removed",
+            DeviceStateReason::Sleeping => "The system is going to sleep",
+            DeviceStateReason::ConnectionRemoved => "The connection profile was removed",
+            DeviceStateReason::UserRequested => "Disconnected by the user",
+            DeviceStateReason::Carrier => "The cable was unplugged",
+            DeviceStateReason::ConnectionAssumed => "An existing connection was taken over",
+            DeviceStateReason::SupplicantAvailable => "The Wi-Fi supplicant became available",
+            DeviceStateReason::ModemNotFound => "The modem could not be found",
+            DeviceStateReason::BluetoothFailed => "The Bluetooth connection failed or timed out",
+            DeviceStateReason::GsmSimNotInserted => "No SIM card is inserted",
+            DeviceStateReason::GsmSimPinRequired => "The SIM card requires a PIN",
+            DeviceStateReason::GsmSimPukRequired => "The SIM card requires a PUK",
+            DeviceStateReason::GsmSimWrong => "The SIM card is not supported",
+            DeviceStateReason::InfinibandMode => {
+                "The InfiniBand device does not support connected mode"
+            }
+            DeviceStateReason::DependencyFailed => "A connection this one depends on failed",
+            DeviceStateReason::Br2684Failed => "The RFC 2684 Ethernet-over-ADSL bridge failed",
+            DeviceStateReason::ModemManagerUnavailable => "ModemManager is not running",
+            DeviceStateReason::SsidNotFound => "The network could not be found",
+            DeviceStateReason::SecondaryConnectionFailed => "A secondary connection failed",
+            DeviceStateReason::DcbFcoeFailed => "DCB or FCoE setup failed",
+            DeviceStateReason::TeamdControlFailed => "The team device could not be controlled",
+            DeviceStateReason::ModemFailed => "The modem failed or is no longer available",
+            DeviceStateReason::ModemAvailable => "The modem is now ready",
+            DeviceStateReason::SimPinIncorrect => "The SIM PIN is wrong",
+            DeviceStateReason::NewActivation => "Another connection was started on the device",
+            DeviceStateReason::ParentChanged => "The parent device changed",
+            DeviceStateReason::ParentManagedChanged => "The parent device is no longer managed",
+            DeviceStateReason::OvsdbFailed => "Open vSwitch could not be configured",
+            DeviceStateReason::IpAddressDuplicate => {
+                "The IP address is already in use on the network"
+            }
+            DeviceStateReason::IpMethodUnsupported => {
+                "The IP configuration method is not supported"
+            }
+            DeviceStateReason::SriovConfigurationFailed => "SR-IOV could not be configured",
+            DeviceStateReason::PeerNotFound => "The Wi-Fi P2P peer could not be found",
+            DeviceStateReason::DeviceHandlerFailed => "The device handler dispatcher script failed",
+        }
+    }
+
+    /// Whether the reason explains a connection failing rather than an expected change such as
+    /// the user disconnecting.
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            DeviceStateReason::None
+                | DeviceStateReason::Unknown
+                | DeviceStateReason::NowManaged
+                | DeviceStateReason::NowUnmanaged
+                | DeviceStateReason::Removed
+                | DeviceStateReason::Sleeping
+                | DeviceStateReason::ConnectionRemoved
+                | DeviceStateReason::UserRequested
+                | DeviceStateReason::ConnectionAssumed
+                | DeviceStateReason::SupplicantAvailable
+                | DeviceStateReason::ModemAvailable
+                | DeviceStateReason::NewActivation
+                | DeviceStateReason::ParentChanged
+                | DeviceStateReason::ParentManagedChanged
+        )
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ActiveConnectionState {
     #[default]
@@ -158,6 +474,100 @@ impl From<u32> for ActiveConnectionState {
     }
 }
 
+/// Why an active connection changed state, as reported along with its `StateChanged` signal.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActiveConnectionStateReason {
+    #[default]
+    Unknown,
+    None,
+    UserDisconnected,
+    DeviceDisconnected,
+    ServiceStopped,
+    IpConfigInvalid,
+    ConnectTimeout,
+    ServiceStartTimeout,
+    ServiceStartFailed,
+    NoSecrets,
+    LoginFailed,
+    ConnectionRemoved,
+    DependencyFailed,
+    DeviceRealizeFailed,
+    DeviceRemoved,
+}
+
+impl From<u32> for ActiveConnectionStateReason {
+    fn from(reason: u32) -> Self {
+        match reason {
+            1 => ActiveConnectionStateReason::None,
+            2 => ActiveConnectionStateReason::UserDisconnected,
+            3 => ActiveConnectionStateReason::DeviceDisconnected,
+            4 => ActiveConnectionStateReason::ServiceStopped,
+            5 => ActiveConnectionStateReason::IpConfigInvalid,
+            6 => ActiveConnectionStateReason::ConnectTimeout,
+            7 => ActiveConnectionStateReason::ServiceStartTimeout,
+            8 => ActiveConnectionStateReason::ServiceStartFailed,
+            9 => ActiveConnectionStateReason::NoSecrets,
+            10 => ActiveConnectionStateReason::LoginFailed,
+            11 => ActiveConnectionStateReason::ConnectionRemoved,
+            12 => ActiveConnectionStateReason::DependencyFailed,
+            13 => ActiveConnectionStateReason::DeviceRealizeFailed,
+            14 => ActiveConnectionStateReason::DeviceRemoved,
+            _ => ActiveConnectionStateReason::Unknown,
+        }
+    }
+}
+
+impl ActiveConnectionStateReason {
+    /// A short explanation that can be shown to the user.
+    pub fn description(&self) -> &'static str {
+        match self {
+            ActiveConnectionStateReason::Unknown => "Unknown error",
+            ActiveConnectionStateReason::None => "No reason given",
+            ActiveConnectionStateReason::UserDisconnected => "Disconnected by the user",
+            ActiveConnectionStateReason::DeviceDisconnected => "The device was disconnected",
+            ActiveConnectionStateReason::ServiceStopped => "The VPN service stopped",
+            ActiveConnectionStateReason::IpConfigInvalid => "The IP configuration was invalid",
+            ActiveConnectionStateReason::ConnectTimeout => "Connecting timed out",
+            ActiveConnectionStateReason::ServiceStartTimeout => {
+                "The VPN service timed out while starting"
+            }
+            ActiveConnectionStateReason::ServiceStartFailed => "The VPN service failed to start",
+            ActiveConnectionStateReason::NoSecrets => "The password or key is missing or wrong",
+            ActiveConnectionStateReason::LoginFailed => "Authentication with the VPN server failed",
+            ActiveConnectionStateReason::ConnectionRemoved => "The connection profile was removed",
+            ActiveConnectionStateReason::DependencyFailed => {
+                "A connection this one depends on failed"
+            }
+            ActiveConnectionStateReason::DeviceRealizeFailed => {
+                "The virtual device could not be created"
+            }
+            ActiveConnectionStateReason::DeviceRemoved => "The device was removed",
+        }
+    }
+
+    /// Whether the reason explains a connection failing rather than an expected change such as
+    /// the user disconnecting.
+    pub fn is_failure(&self) -> bool {
+        !matches!(
+            self,
+            ActiveConnectionStateReason::Unknown
+                | ActiveConnectionStateReason::None
+                | ActiveConnectionStateReason::UserDisconnected
+                | ActiveConnectionStateReason::ConnectionRemoved
+                | ActiveConnectionStateReason::DeviceRemoved
+        )
+    }
+}
+
+bitflags! {
+    /// `NMRadioFlags`: the kinds of radio present on the machine.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct RadioFlags: u32 {
+        const WLAN_AVAILABLE = 0x1;
+        const WWAN_AVAILABLE = 0x2;
+    }
+}
+
 bitflags! {
     pub struct ApFlags: u32 {
         const NONE = 0x0;
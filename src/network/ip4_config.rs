@@ -0,0 +1,30 @@
+use zbus::Result;
+
+use crate::interfaces::ip4_config::IP4ConfigProxy;
+
+#[derive(Debug, Clone)]
+pub struct Ip4Config {
+    ip4_config: IP4ConfigProxy<'static>,
+}
+
+impl Ip4Config {
+    pub fn new(ip4_config: IP4ConfigProxy<'static>) -> Self {
+        Self { ip4_config }
+    }
+
+    /// Addresses in CIDR notation, e.g. `192.168.1.10/24`.
+    pub async fn addresses(&self) -> Result<Vec<String>> {
+        let address_data = self.ip4_config.address_data().await?;
+
+        let addresses = address_data
+            .iter()
+            .filter_map(|data| {
+                let address = String::try_from(data.get("address")?.try_clone().ok()?).ok()?;
+                let prefix = u32::try_from(data.get("prefix")?).ok()?;
+                Some(format!("{}/{}", address, prefix))
+            })
+            .collect();
+
+        Ok(addresses)
+    }
+}
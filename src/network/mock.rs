@@ -0,0 +1,284 @@
+//! A stand-in for NetworkManager on a peer-to-peer connection, so that tests can serve mock
+//! objects and count the calls made against them.
+
+use futures::StreamExt;
+use tokio::{
+    net::UnixStream,
+    sync::mpsc::{UnboundedReceiver, unbounded_channel},
+};
+use zbus::{
+    Connection, Guid, MessageStream, connection, interface, message::Type,
+    object_server::Interface, zvariant::OwnedObjectPath,
+};
+
+const SYNC_PATH: &str = "/mock";
+const SYNC_INTERFACE: &str = "mock.Sync";
+
+/// A method call received by the mock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub path: String,
+    pub member: String,
+}
+
+pub struct MockBus {
+    /// The connection the code under test talks through.
+    pub connection: Connection,
+    server: Connection,
+    calls: UnboundedReceiver<Call>,
+}
+
+impl MockBus {
+    pub async fn new() -> Self {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+
+        let server = connection::Builder::unix_stream(server_stream)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at(SYNC_PATH, Sync)
+            .unwrap()
+            .build();
+        let client = connection::Builder::unix_stream(client_stream)
+            .p2p()
+            .build();
+        let (server, connection) = futures::try_join!(server, client).unwrap();
+
+        let (calls_tx, calls) = unbounded_channel();
+        let mut messages = MessageStream::from(&server);
+        tokio::spawn(async move {
+            while let Some(Ok(message)) = messages.next().await {
+                let header = message.header();
+                if header.message_type() != Type::MethodCall {
+                    continue;
+                }
+
+                let call = Call {
+                    path: header
+                        .path()
+                        .map(|path| path.to_string())
+                        .unwrap_or_default(),
+                    member: header
+                        .member()
+                        .map(|member| member.to_string())
+                        .unwrap_or_default(),
+                };
+                if calls_tx.send(call).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self {
+            connection,
+            server,
+            calls,
+        }
+    }
+
+    pub async fn serve(&self, path: &str, interface: impl Interface) {
+        self.server
+            .object_server()
+            .at(path, interface)
+            .await
+            .unwrap();
+    }
+
+    /// The calls received since the last time this was called.
+    pub async fn take_calls(&mut self) -> Vec<Call> {
+        // Calls arrive in order, so once the sync call is seen every call before it has been too.
+        self.connection
+            .call_method(None::<&str>, SYNC_PATH, Some(SYNC_INTERFACE), "Sync", &())
+            .await
+            .unwrap();
+
+        let mut calls = Vec::new();
+        while let Some(call) = self.calls.recv().await {
+            if call.path == SYNC_PATH {
+                break;
+            }
+            calls.push(call);
+        }
+        calls
+    }
+}
+
+struct Sync;
+
+#[interface(name = "mock.Sync")]
+impl Sync {
+    fn sync(&self) {}
+}
+
+pub struct MockWireless {
+    pub access_points: Vec<OwnedObjectPath>,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device.Wireless")]
+impl MockWireless {
+    #[zbus(property)]
+    fn access_points(&self) -> Vec<OwnedObjectPath> {
+        self.access_points.clone()
+    }
+}
+
+pub struct MockAccessPoint {
+    pub ssid: String,
+    pub strength: u8,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.AccessPoint")]
+impl MockAccessPoint {
+    #[zbus(property)]
+    fn ssid(&self) -> Vec<u8> {
+        self.ssid.as_bytes().to_vec()
+    }
+
+    #[zbus(property)]
+    fn hw_address(&self) -> String {
+        "00:11:22:33:44:55".into()
+    }
+
+    #[zbus(property)]
+    fn strength(&self) -> u8 {
+        self.strength
+    }
+
+    #[zbus(property)]
+    fn flags(&self) -> u32 {
+        1
+    }
+
+    #[zbus(property)]
+    fn wpa_flags(&self) -> u32 {
+        0
+    }
+
+    #[zbus(property)]
+    fn rsn_flags(&self) -> u32 {
+        0x100
+    }
+}
+
+/// NetworkManager itself. Every object reference is unset and Wi-Fi is on unless a test says
+/// otherwise.
+pub struct MockNetworkManager {
+    pub state: u32,
+    pub devices: Vec<OwnedObjectPath>,
+    pub active_connections: Vec<OwnedObjectPath>,
+    pub primary_connection: OwnedObjectPath,
+    pub activating_connection: OwnedObjectPath,
+    pub wireless_hardware_enabled: bool,
+}
+
+impl Default for MockNetworkManager {
+    fn default() -> Self {
+        Self {
+            state: 0,
+            devices: vec![],
+            active_connections: vec![],
+            primary_connection: path("/"),
+            activating_connection: path("/"),
+            wireless_hardware_enabled: true,
+        }
+    }
+}
+
+#[interface(name = "org.freedesktop.NetworkManager")]
+impl MockNetworkManager {
+    fn get_devices(&self) -> Vec<OwnedObjectPath> {
+        self.devices.clone()
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+
+    #[zbus(property)]
+    fn active_connections(&self) -> Vec<OwnedObjectPath> {
+        self.active_connections.clone()
+    }
+
+    #[zbus(property)]
+    fn primary_connection(&self) -> OwnedObjectPath {
+        self.primary_connection.clone()
+    }
+
+    #[zbus(property)]
+    fn activating_connection(&self) -> OwnedObjectPath {
+        self.activating_connection.clone()
+    }
+
+    #[zbus(property)]
+    fn wireless_enabled(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn wireless_hardware_enabled(&self) -> bool {
+        self.wireless_hardware_enabled
+    }
+
+    #[zbus(property)]
+    fn radio_flags(&self) -> u32 {
+        // WLAN_AVAILABLE
+        0x1
+    }
+}
+
+pub struct MockDevice {
+    pub device_type: u32,
+    pub state: u32,
+    pub state_reason: u32,
+    pub active_connection: OwnedObjectPath,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Device")]
+impl MockDevice {
+    #[zbus(property)]
+    fn device_type(&self) -> u32 {
+        self.device_type
+    }
+
+    #[zbus(property)]
+    fn interface(&self) -> String {
+        "wlan0".into()
+    }
+
+    #[zbus(property)]
+    fn state(&self) -> u32 {
+        self.state
+    }
+
+    #[zbus(property)]
+    fn state_reason(&self) -> (u32, u32) {
+        (self.state, self.state_reason)
+    }
+
+    #[zbus(property)]
+    fn active_connection(&self) -> OwnedObjectPath {
+        self.active_connection.clone()
+    }
+
+    #[zbus(property)]
+    fn available_connections(&self) -> Vec<OwnedObjectPath> {
+        vec![]
+    }
+}
+
+pub struct MockActiveConnection {
+    pub id: String,
+}
+
+#[interface(name = "org.freedesktop.NetworkManager.Connection.Active")]
+impl MockActiveConnection {
+    #[zbus(property)]
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+}
+
+pub fn path(path: &str) -> OwnedObjectPath {
+    OwnedObjectPath::try_from(path).unwrap()
+}
@@ -3,6 +3,41 @@ pub mod active_connection;
 pub mod device;
 pub mod devices;
 pub mod enums;
+pub mod ip4_config;
+#[cfg(test)]
+pub mod mock;
 pub mod network_manager;
+pub mod properties;
 pub mod settings;
 
+use zbus::zvariant::ObjectPath;
+
+pub const NETWORK_MANAGER_SERVICE: &str = "org.freedesktop.NetworkManager";
+
+/// NetworkManager uses the root path `/` for object references that are not set. An empty path
+/// isn't valid on the bus, so it can't refer to anything either.
+pub fn is_unset(path: &ObjectPath<'_>) -> bool {
+    matches!(path.as_str(), "/" | "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn root_path_is_unset() {
+        assert!(is_unset(&ObjectPath::from_static_str_unchecked("/")));
+    }
+
+    #[test]
+    fn empty_path_is_unset() {
+        assert!(is_unset(&ObjectPath::from_static_str_unchecked("")));
+    }
+
+    #[test]
+    fn object_path_is_set() {
+        assert!(!is_unset(&ObjectPath::from_static_str_unchecked(
+            "/org/freedesktop/NetworkManager/ActiveConnection/1"
+        )));
+    }
+}
@@ -1,19 +1,20 @@
 use anyhow::Result;
-use anyhow::bail;
 use futures::StreamExt;
-use log::info;
-use tokio::process::Command;
+use log::{info, warn};
 use zbus::Connection;
 use zbus::Result as ZbusResult;
-use zbus::zvariant::ObjectPath;
+use zbus::fdo::DBusProxy;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath};
 
 use super::active_connection::ActiveConnection;
 use super::device::Device;
-use super::devices::SpecificDevice;
+use super::enums::DeviceState;
+use super::enums::DeviceStateReason;
 use super::enums::NmConnectivityState;
 use super::enums::NmState;
-use crate::interfaces::active::ActiveProxy;
-use crate::interfaces::{device::DeviceProxy, network_manager::NetworkManagerProxy};
+use super::enums::RadioFlags;
+use super::{NETWORK_MANAGER_SERVICE, is_unset};
+use crate::interfaces::network_manager::NetworkManagerProxy;
 
 #[derive(Debug, Clone)]
 pub struct NetworkManager {
@@ -27,6 +28,44 @@ impl NetworkManager {
         Ok(Self { connection, nm })
     }
 
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub async fn is_running(&self) -> Result<bool> {
+        let dbus = DBusProxy::new(&self.connection).await?;
+        let running = dbus
+            .name_has_owner(NETWORK_MANAGER_SERVICE.try_into()?)
+            .await?;
+        Ok(running)
+    }
+
+    /// Calls `f` with `true` when NetworkManager appears on the bus and `false` when it goes away.
+    /// Returns once the bus connection itself is gone.
+    pub async fn listening_to_service_changes<F>(&self, f: F) -> Result<()>
+    where
+        F: AsyncFnOnce(bool) -> () + Send + Copy,
+    {
+        let dbus = DBusProxy::new(&self.connection).await?;
+        let mut stream = dbus
+            .receive_name_owner_changed_with_args(&[(0, NETWORK_MANAGER_SERVICE)])
+            .await?;
+
+        while let Some(name_owner_changed) = stream.next().await {
+            let running = match name_owner_changed.args() {
+                Ok(args) => args.new_owner().is_some(),
+                Err(e) => {
+                    anyhow::bail!("Failed to get NameOwnerChanged arguments: {e}");
+                }
+            };
+
+            info!("NetworkManager running: {}", running);
+
+            f(running).await;
+        }
+        Ok(())
+    }
+
     pub async fn listening_to_state_changes<F>(&self, f: F) -> Result<()>
     where
         F: AsyncFnOnce(NmState) -> () + Send + Copy,
@@ -48,9 +87,90 @@ impl NetworkManager {
         Ok(())
     }
 
+    /// Reports state changes of the devices present when called. Devices added later are not
+    /// covered, so this needs to be called again after `DeviceAdded`.
+    pub async fn listening_to_device_state_changes<F>(&self, f: F) -> Result<()>
+    where
+        F: AsyncFnOnce(DeviceState, DeviceStateReason) -> () + Send + Copy,
+    {
+        let mut streams = Vec::new();
+        for device in self.devices().await? {
+            streams.push(device.receive_state_changed_signal().await?);
+        }
+
+        let mut stream = futures::stream::select_all(streams);
+
+        while let Some(state_changed) = stream.next().await {
+            let (state, reason) = match state_changed.args() {
+                Ok(args) => (
+                    DeviceState::from(args.new_state),
+                    DeviceStateReason::from(args.reason),
+                ),
+                Err(e) => {
+                    anyhow::bail!("Failed to get device StateChanged arguments: {e}");
+                }
+            };
+
+            info!("Device state changed: {:?} ({:?})", state, reason);
+
+            f(state, reason).await;
+        }
+        Ok(())
+    }
+
+    /// Calls `f` whenever another connection becomes the primary one, or there stops being one.
+    pub async fn listening_to_primary_connection_changes<F>(&self, f: F) -> Result<()>
+    where
+        F: AsyncFnOnce() -> () + Send + Copy,
+    {
+        let mut stream = self.nm.receive_primary_connection_changed().await;
+        while stream.next().await.is_some() {
+            info!("Primary connection changed");
+            f().await;
+        }
+        Ok(())
+    }
+
+    /// Calls `f` whenever Wi-Fi or WWAN is turned on or off, in software or by a hardware switch.
+    pub async fn listening_to_radio_changes<F>(&self, f: F) -> Result<()>
+    where
+        F: AsyncFnOnce() -> () + Send + Copy,
+    {
+        let mut stream = futures::stream::select_all([
+            self.nm
+                .receive_wireless_enabled_changed()
+                .await
+                .map(|_| ())
+                .boxed(),
+            self.nm
+                .receive_wireless_hardware_enabled_changed()
+                .await
+                .map(|_| ())
+                .boxed(),
+            self.nm
+                .receive_wwan_enabled_changed()
+                .await
+                .map(|_| ())
+                .boxed(),
+            self.nm
+                .receive_wwan_hardware_enabled_changed()
+                .await
+                .map(|_| ())
+                .boxed(),
+        ]);
+
+        while stream.next().await.is_some() {
+            info!("Radio state changed");
+            f().await;
+        }
+        Ok(())
+    }
+
+    /// Calls `f` with the path of every device NetworkManager adds. The device isn't loaded, as it
+    /// may already be gone again.
     pub async fn listening_to_device_added<F>(&self, f: F) -> Result<()>
     where
-        F: AsyncFnOnce(Option<SpecificDevice>) -> () + Send + Copy,
+        F: AsyncFnOnce(OwnedObjectPath) -> () + Send + Copy,
     {
         let mut stream = self.nm.receive_device_added().await?;
 
@@ -63,32 +183,20 @@ impl NetworkManager {
                 }
             };
 
-            let device = match DeviceProxy::builder(&self.connection)
-                .path(&device_path)
-                .unwrap()
-                .build()
-                .await
-            {
-                Ok(device_proxy) => Device::new(device_proxy).to_specific_device().await,
-                Err(e) => {
-                    anyhow::bail!("Failed to build DeviceProxy: {e}");
-                }
-            };
-
-            f(device).await;
+            info!("Device added: {}", device_path);
 
-            info!("Device added");
+            f(device_path.into()).await;
         }
         Ok(())
     }
 
+    /// Calls `f` with the path of every device NetworkManager removes.
     pub async fn listening_to_device_removed<F>(&self, f: F) -> Result<()>
     where
-        F: AsyncFnOnce(Option<SpecificDevice>) -> () + Send + Copy,
+        F: AsyncFnOnce(OwnedObjectPath) -> () + Send + Copy,
     {
         let mut stream = self.nm.receive_device_removed().await?;
         while let Some(device_removed) = stream.next().await {
-            info!("Device removed");
             let device_path: ObjectPath<'static> = match device_removed.args() {
                 Ok(args) => args.device_path().to_owned(),
                 Err(e) => {
@@ -96,19 +204,9 @@ impl NetworkManager {
                 }
             };
 
-            let device = match DeviceProxy::builder(&self.connection)
-                .path(&device_path)
-                .unwrap()
-                .build()
-                .await
-            {
-                Ok(device_proxy) => Device::new(device_proxy).to_specific_device().await,
-                Err(e) => {
-                    anyhow::bail!("Failed to build DeviceProxy: {e}");
-                }
-            };
+            info!("Device removed: {}", device_path);
 
-            f(device).await;
+            f(device_path.into()).await;
         }
         Ok(())
     }
@@ -126,72 +224,86 @@ impl NetworkManager {
         let mut out = Vec::with_capacity(devices.len());
 
         for device in devices {
-            // BUG: It's possible for device to be an ObjectPath("/") which means that the device
-            // is not set. This will cause panics when you try to acess properties
-            // and call methods on it. Until this is fixed, we need to check if the device
-            // doesn't throw an error on any of the methods.
-            // In the future, invalid devices will probably be filtered out from the list
-            let device = DeviceProxy::builder(&self.connection)
-                .path(device)?
-                .build()
-                .await?;
-            out.push(Device::new(device));
+            if is_unset(&device) {
+                continue;
+            }
+
+            // A device can go away between listing and loading it.
+            match Device::from_connection_and_path(&self.connection, device.clone()).await {
+                Ok(device) => out.push(device),
+                Err(e) => warn!("Skipping device {}: {}", device, e),
+            }
         }
 
         Ok(out)
     }
 
-    pub async fn all_devices(&self) -> Result<Vec<Device>> {
-        let devices = self.nm.get_devices().await?;
+    pub async fn state(&self) -> ZbusResult<NmState> {
+        self.nm.state().await.map(NmState::from)
+    }
 
-        let mut out = Vec::with_capacity(devices.len());
+    /// URI NetworkManager probes to check connectivity. Empty when checks are not configured.
+    pub async fn connectivity_check_uri(&self) -> ZbusResult<String> {
+        self.nm.connectivity_check_uri().await
+    }
 
-        for device in devices {
-            // BUG: It's possible for device to be an ObjectPath("/") which means that the device
-            // is not set. This will cause panics when you try to acess properties
-            // and call methods on it. Until this is fixed, we need to check if the device
-            // doesn't throw an error on any of the methods.
-            // In the future, invalid devices will probably be filtered out from the list
-            let deice = Device::from_connection_and_path(&self.connection, device).await;
-            out.push(deice);
-        }
+    pub async fn connectivity_check_available(&self) -> ZbusResult<bool> {
+        self.nm.connectivity_check_available().await
+    }
 
-        Ok(out)
+    pub async fn connectivity_check_enabled(&self) -> ZbusResult<bool> {
+        self.nm.connectivity_check_enabled().await
     }
 
-    pub async fn state(&self) -> ZbusResult<NmState> {
-        self.nm.state().await.map(NmState::from)
+    pub async fn set_connectivity_check_enabled(&self, enabled: bool) -> ZbusResult<()> {
+        self.nm.set_connectivity_check_enabled(enabled).await
     }
 
     pub async fn connectivity(&self) -> ZbusResult<NmConnectivityState> {
         self.nm.connectivity().await.map(NmConnectivityState::from)
     }
 
-    pub async fn primary_connection(&self) -> ZbusResult<ActiveConnection> {
-        // BUG: It's possible for self.nm.primary_connection() to return an ObjectPath("/") which means that an
-        // active connection is not set. This will cause panics when you try to acess properties
-        // and call methods on it. Until this is fixed, we need to check if the active connection
-        // doesn't throw an error on any of the methods.
-        // In the future, this will probably return an Option<ActiveConnection> instead of
-        // a Result<ActiveConnection> and handle the error in the caller.
-
+    pub async fn primary_connection(&self) -> ZbusResult<Option<ActiveConnection>> {
         let primary_connection = self.nm.primary_connection().await?;
-        let primary_connection = ActiveProxy::builder(&self.connection)
-            .path(primary_connection)?
-            .build()
-            .await?;
-        Ok(ActiveConnection::new(primary_connection))
+        self.active_connection_from_path(primary_connection).await
+    }
+
+    pub async fn activating_connection(&self) -> ZbusResult<Option<ActiveConnection>> {
+        let activating_connection = self.nm.activating_connection().await?;
+        self.active_connection_from_path(activating_connection)
+            .await
+    }
+
+    async fn active_connection_from_path(
+        &self,
+        path: OwnedObjectPath,
+    ) -> ZbusResult<Option<ActiveConnection>> {
+        if is_unset(&path) {
+            return Ok(None);
+        }
+
+        let active_connection =
+            ActiveConnection::from_connection_and_path(&self.connection, path).await?;
+        Ok(Some(active_connection))
     }
 
     pub async fn active_connections(&self) -> Result<Vec<ActiveConnection>> {
         let active_connections = self.nm.active_connections().await?;
         let mut out = Vec::with_capacity(active_connections.len());
         for active_connection in active_connections {
-            let active_connection = ActiveProxy::builder(&self.connection)
-                .path(active_connection)?
-                .build()
-                .await?;
-            out.push(ActiveConnection::new(active_connection));
+            if is_unset(&active_connection) {
+                continue;
+            }
+
+            match ActiveConnection::from_connection_and_path(
+                &self.connection,
+                active_connection.clone(),
+            )
+            .await
+            {
+                Ok(active_connection) => out.push(active_connection),
+                Err(e) => warn!("Skipping active connection {}: {}", active_connection, e),
+            }
         }
         Ok(out)
     }
@@ -216,6 +328,15 @@ impl NetworkManager {
         Ok(())
     }
 
+    pub async fn networking_enabled(&self) -> ZbusResult<bool> {
+        self.nm.networking_enabled().await
+    }
+
+    /// Turns all networking on or off. Unlike airplane mode this leaves the radios alone.
+    pub async fn set_networking_enabled(&self, enabled: bool) -> ZbusResult<()> {
+        self.nm.enable(enabled).await
+    }
+
     pub async fn wifi_enabled(&self) -> Result<bool> {
         Ok(self.nm.wireless_enabled().await?)
     }
@@ -225,51 +346,147 @@ impl NetworkManager {
         Ok(())
     }
 
-    pub async fn bluetooth_enabled(&self) -> Result<bool> {
-        let cmd = Command::new("rfkill")
-            .arg("list")
-            .arg("bluetooth")
-            .output()
-            .await?;
-        if !cmd.status.success() {
-            bail!("Failed to list bluetooth devices");
-        }
+    /// False while a hardware switch has the Wi-Fi radio turned off.
+    pub async fn wifi_hardware_enabled(&self) -> Result<bool> {
+        Ok(self.nm.wireless_hardware_enabled().await?)
+    }
 
-        let output = String::from_utf8(cmd.stdout)?;
+    pub async fn wwan_enabled(&self) -> Result<bool> {
+        Ok(self.nm.wwan_enabled().await?)
+    }
 
-        let bluetooth_enabled = output.contains("Soft blocked: no");
-        Ok(bluetooth_enabled)
+    pub async fn set_wwan_enabled(&self, enabled: bool) -> Result<()> {
+        self.nm.set_wwan_enabled(enabled).await?;
+        Ok(())
     }
 
-    pub async fn airplane_mode_enabled(&self) -> Result<bool> {
-        let wifi_enabled = self.wifi_enabled().await?;
-        let bluetooth_enabled = self.bluetooth_enabled().await?;
+    /// False while a hardware switch has the WWAN radio turned off.
+    pub async fn wwan_hardware_enabled(&self) -> Result<bool> {
+        Ok(self.nm.wwan_hardware_enabled().await?)
+    }
 
-        if !wifi_enabled && !bluetooth_enabled {
-            Ok(true)
-        } else {
-            Ok(false)
-        }
+    /// Which kinds of radio the machine has.
+    pub async fn radio_flags(&self) -> Result<RadioFlags> {
+        let radio_flags = self.nm.radio_flags().await?;
+        Ok(RadioFlags::from_bits_truncate(radio_flags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::mock::{
+        MockActiveConnection, MockBus, MockDevice, MockNetworkManager, path,
+    };
+
+    const NETWORK_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
+    const ACTIVE_CONNECTION_PATH: &str = "/org/freedesktop/NetworkManager/ActiveConnection/1";
+
+    async fn network_manager(bus: &MockBus, mock: MockNetworkManager) -> NetworkManager {
+        bus.serve(NETWORK_MANAGER_PATH, mock).await;
+        bus.serve(
+            ACTIVE_CONNECTION_PATH,
+            MockActiveConnection { id: "Home".into() },
+        )
+        .await;
+
+        NetworkManager::new(bus.connection.clone()).await.unwrap()
     }
 
-    pub async fn set_airplane_mode_enabled(&self, on: bool) -> Result<()> {
-        let cmd = Command::new("rfkill")
-            .arg(if on { "block" } else { "unblock" })
-            .arg("bluetooth")
-            .output()
-            .await?;
+    #[tokio::test]
+    async fn unset_primary_connection_is_none() {
+        let mut bus = MockBus::new().await;
+        let network_manager = network_manager(&bus, MockNetworkManager::default()).await;
 
-        if !cmd.status.success() {
-            anyhow::bail!("Failed to set bluetooth enabled");
-        }
+        assert!(
+            network_manager
+                .primary_connection()
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            network_manager
+                .activating_connection()
+                .await
+                .unwrap()
+                .is_none()
+        );
 
-        match self.set_wifi_enabled(!on).await {
-            Ok(_) => {}
-            Err(e) => {
-                anyhow::bail!("Failed to set wifi enabled: {}", e);
-            }
-        };
+        // Nothing is loaded from `/`.
+        let calls = bus.take_calls().await;
+        assert!(calls.iter().all(|call| call.path != "/"));
+    }
 
-        Ok(())
+    #[tokio::test]
+    async fn set_primary_connection_is_loaded() {
+        let mut bus = MockBus::new().await;
+        let network_manager = network_manager(
+            &bus,
+            MockNetworkManager {
+                primary_connection: path(ACTIVE_CONNECTION_PATH),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let primary_connection = network_manager.primary_connection().await.unwrap().unwrap();
+        assert_eq!(primary_connection.id().unwrap(), "Home");
+
+        let calls = bus.take_calls().await;
+        assert!(calls.iter().any(|call| call.path == ACTIVE_CONNECTION_PATH));
+    }
+
+    #[tokio::test]
+    async fn vanished_devices_are_skipped() {
+        const DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/1";
+        const VANISHED_DEVICE_PATH: &str = "/org/freedesktop/NetworkManager/Devices/2";
+
+        let bus = MockBus::new().await;
+        bus.serve(
+            DEVICE_PATH,
+            MockDevice {
+                device_type: 2,
+                state: 100,
+                state_reason: 0,
+                active_connection: path("/"),
+            },
+        )
+        .await;
+        let network_manager = network_manager(
+            &bus,
+            MockNetworkManager {
+                devices: vec![path(VANISHED_DEVICE_PATH), path(DEVICE_PATH)],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let devices = network_manager.devices().await.unwrap();
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].path().as_str(), DEVICE_PATH);
+    }
+
+    #[tokio::test]
+    async fn vanished_active_connections_are_skipped() {
+        const VANISHED_ACTIVE_CONNECTION_PATH: &str =
+            "/org/freedesktop/NetworkManager/ActiveConnection/2";
+
+        let bus = MockBus::new().await;
+        let network_manager = network_manager(
+            &bus,
+            MockNetworkManager {
+                active_connections: vec![
+                    path(ACTIVE_CONNECTION_PATH),
+                    path(VANISHED_ACTIVE_CONNECTION_PATH),
+                ],
+                ..Default::default()
+            },
+        )
+        .await;
+
+        let active_connections = network_manager.active_connections().await.unwrap();
+        assert_eq!(active_connections.len(), 1);
+        assert_eq!(active_connections[0].id().unwrap(), "Home");
     }
 }
@@ -0,0 +1,166 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock, Mutex, RwLock},
+};
+
+use anyhow::Result as AnyResult;
+use futures::StreamExt;
+use log::debug;
+use tokio::task::JoinHandle;
+use zbus::{
+    Connection, Result,
+    fdo::{ObjectManagerProxy, PropertiesProxy},
+    names::InterfaceName,
+    proxy::CacheProperties,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+};
+
+use super::NETWORK_MANAGER_SERVICE;
+
+type CacheKey = (OwnedObjectPath, &'static str);
+
+static CACHE: LazyLock<Mutex<HashMap<CacheKey, CachedProperties>>> =
+    LazyLock::new(Default::default);
+
+#[derive(Debug)]
+struct Listener(JoinHandle<()>);
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// All properties of one interface on one object, fetched with a single `GetAll` and kept up to
+/// date from `PropertiesChanged`. Instances are shared, so loading the same object again is free
+/// until it is evicted.
+#[derive(Debug, Clone)]
+pub struct CachedProperties {
+    values: Arc<RwLock<HashMap<String, OwnedValue>>>,
+    _listener: Arc<Listener>,
+}
+
+impl CachedProperties {
+    pub async fn load(
+        connection: &Connection,
+        path: &ObjectPath<'_>,
+        interface: &'static str,
+    ) -> Result<Self> {
+        let key = (OwnedObjectPath::from(path.to_owned()), interface);
+
+        if let Some(cached) = CACHE.lock().unwrap().get(&key) {
+            return Ok(cached.clone());
+        }
+
+        debug!("Loading {} properties of {}", interface, path);
+
+        let properties = PropertiesProxy::builder(connection)
+            .destination(NETWORK_MANAGER_SERVICE)?
+            .path(path.to_owned())?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        // Subscribe before fetching so that no change can slip in between the two.
+        let mut changes = properties.receive_properties_changed().await?;
+
+        let interface_name = InterfaceName::from_static_str(interface)?;
+        let values = properties.get_all(interface_name.clone()).await?;
+        let values = Arc::new(RwLock::new(values));
+
+        let listener = tokio::spawn({
+            let values = values.clone();
+            async move {
+                while let Some(properties_changed) = changes.next().await {
+                    let Ok(args) = properties_changed.args() else {
+                        continue;
+                    };
+
+                    if args.interface_name() != &interface_name {
+                        continue;
+                    }
+
+                    let mut values = values.write().unwrap();
+
+                    for (name, value) in args.changed_properties() {
+                        if let Ok(value) = value.try_to_owned() {
+                            values.insert(name.to_string(), value);
+                        }
+                    }
+
+                    for name in args.invalidated_properties().iter() {
+                        values.remove(*name);
+                    }
+                }
+            }
+        });
+
+        let cached = Self {
+            values,
+            _listener: Arc::new(Listener(listener)),
+        };
+
+        CACHE.lock().unwrap().insert(key, cached.clone());
+
+        Ok(cached)
+    }
+
+    pub fn get<T>(&self, name: &str) -> Result<T>
+    where
+        T: TryFrom<OwnedValue>,
+        T::Error: Into<zbus::Error>,
+    {
+        let values = self.values.read().unwrap();
+
+        let value = match values.get(name) {
+            Some(value) => value.try_clone()?,
+            None => {
+                return Err(zbus::Error::Failure(format!(
+                    "Property {} is not available",
+                    name
+                )));
+            }
+        };
+
+        T::try_from(value).map_err(Into::into)
+    }
+}
+
+/// Drops every cached object. Needed when NetworkManager restarts since it reuses object paths.
+pub fn clear_cache() {
+    CACHE.lock().unwrap().clear();
+}
+
+fn evict(path: &ObjectPath<'_>) {
+    CACHE
+        .lock()
+        .unwrap()
+        .retain(|(cached_path, _), _| cached_path.as_str() != path.as_str());
+}
+
+/// Evicts objects from the cache as NetworkManager removes them, e.g. access points going out of
+/// range, so the cache doesn't grow with every scan.
+pub async fn listening_to_removed_objects(connection: &Connection) -> AnyResult<()> {
+    let object_manager = ObjectManagerProxy::builder(connection)
+        .destination(NETWORK_MANAGER_SERVICE)?
+        .path("/org/freedesktop")?
+        .build()
+        .await?;
+
+    let mut stream = object_manager.receive_interfaces_removed().await?;
+
+    while let Some(interfaces_removed) = stream.next().await {
+        let object_path = match interfaces_removed.args() {
+            Ok(args) => args.object_path().to_owned(),
+            Err(e) => {
+                anyhow::bail!("Failed to get InterfacesRemoved arguments: {e}");
+            }
+        };
+
+        debug!("Evicting {} from the properties cache", object_path);
+
+        evict(&object_path);
+    }
+
+    Ok(())
+}
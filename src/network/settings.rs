@@ -1,5 +1,7 @@
+use std::collections::HashMap;
+
 use anyhow::Result;
-use zbus::zvariant::ObjectPath;
+use zbus::zvariant::{ObjectPath, OwnedValue};
 
 use crate::interfaces::settings::connection::ConnectionProxy;
 
@@ -23,6 +25,27 @@ impl ConnectionSetting {
         }
     }
 
+    /// Endpoints of the peers of a WireGuard connection.
+    pub async fn wireguard_endpoints(&self) -> Result<Vec<String>> {
+        let settings = self.settings.get_settings().await?;
+        let Some(peers) = settings
+            .get("wireguard")
+            .and_then(|wireguard| wireguard.get("peers"))
+        else {
+            return Ok(vec![]);
+        };
+
+        let peers = Vec::<HashMap<String, OwnedValue>>::try_from(peers.try_clone()?)?;
+
+        let endpoints = peers
+            .into_iter()
+            .filter_map(|mut peer| peer.remove("endpoint"))
+            .filter_map(|endpoint| String::try_from(endpoint).ok())
+            .collect();
+
+        Ok(endpoints)
+    }
+
     pub fn path(&self) -> ObjectPath<'static> {
         self.settings.inner().path().clone()
     }
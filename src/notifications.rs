@@ -0,0 +1,92 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::Result;
+use tokio::sync::{Mutex, OnceCell};
+use zbus::{Connection, zvariant::Value};
+
+use crate::interfaces::notifications::NotificationsProxy;
+
+const APP_NAME: &str = "Networkless";
+/// Lets the notification server pick how long notifications stay up.
+const DEFAULT_EXPIRE_TIMEOUT: i32 = -1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
+    /// Connected to or disconnected from a network.
+    Connection,
+    /// A VPN connection went up or down.
+    Vpn,
+    /// A connection could not be activated.
+    ActivationFailed,
+    /// Access to the internet was lost or became limited.
+    Connectivity,
+    /// The network requires signing in through a web page.
+    CaptivePortal,
+}
+
+impl Category {
+    fn icon(&self) -> &'static str {
+        match self {
+            Category::Connection => "network-transmit-receive-symbolic",
+            Category::Vpn => "network-vpn-symbolic",
+            Category::ActivationFailed => "network-error-symbolic",
+            Category::Connectivity => "network-no-route-symbolic",
+            Category::CaptivePortal => "web-browser-symbolic",
+        }
+    }
+
+    /// Notification category as defined by the desktop notifications specification.
+    fn hint(&self) -> &'static str {
+        match self {
+            Category::ActivationFailed => "network.error",
+            _ => "network",
+        }
+    }
+}
+
+/// Client for the desktop's notification server.
+///
+/// Each category keeps a single notification on screen: a new notification replaces the last one
+/// shown for its category, so rapid changes don't pile up.
+#[derive(Debug, Clone, Default)]
+pub struct Notifier {
+    notifications: Arc<OnceCell<NotificationsProxy<'static>>>,
+    ids: Arc<Mutex<HashMap<Category, u32>>>,
+}
+
+impl Notifier {
+    pub async fn notify(&self, category: Category, summary: &str, body: &str) -> Result<()> {
+        let notifications = self
+            .notifications
+            .get_or_try_init(|| async {
+                let connection = Connection::session().await?;
+                NotificationsProxy::new(&connection).await
+            })
+            .await?;
+
+        // Held until the new id is stored so that concurrent notifications of the same category
+        // replace each other as well.
+        let mut ids = self.ids.lock().await;
+        let replaces_id = ids.get(&category).copied().unwrap_or_default();
+
+        let hint = Value::from(category.hint());
+        let hints = HashMap::from([("category", &hint)]);
+
+        let id = notifications
+            .notify(
+                APP_NAME,
+                replaces_id,
+                category.icon(),
+                summary,
+                body,
+                &[],
+                hints,
+                DEFAULT_EXPIRE_TIMEOUT,
+            )
+            .await?;
+
+        ids.insert(category, id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use zbus::Connection;
+
+use crate::{
+    airplane_mode,
+    app::tray_connectivity,
+    network::{
+        devices::SpecificDevice,
+        enums::{DeviceType, RadioFlags},
+        network_manager::NetworkManager,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    Status,
+    WifiList,
+    VpnList,
+    Devices,
+}
+
+/// A one-shot look at the state of the network, printed as a table or as JSON. Only needs the
+/// system bus, so it works whether or not the applet is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Report {
+    query: Query,
+    json: bool,
+}
+
+impl Report {
+    pub fn new(query: Query, json: bool) -> Self {
+        Self { query, json }
+    }
+
+    pub async fn print(&self) -> Result<()> {
+        let connection = Connection::system().await?;
+        let network_manager = NetworkManager::new(connection).await?;
+
+        if !network_manager.is_running().await? {
+            bail!("NetworkManager is not running");
+        }
+
+        match self.query {
+            Query::Status => {
+                let status = status(&network_manager).await?;
+                match self.json {
+                    true => print_json(&status),
+                    false => {
+                        print_table(&["", ""], status.rows());
+                        Ok(())
+                    }
+                }
+            }
+            Query::WifiList => {
+                let networks = wifi_networks(&network_manager).await?;
+                match self.json {
+                    true => print_json(&networks),
+                    false => {
+                        print_table(
+                            &["IN-USE", "SSID", "SIGNAL", "SECURE", "KNOWN"],
+                            networks.iter().map(WifiNetwork::row).collect(),
+                        );
+                        Ok(())
+                    }
+                }
+            }
+            Query::VpnList => {
+                let connections = vpn_connections(&network_manager).await?;
+                match self.json {
+                    true => print_json(&connections),
+                    false => {
+                        print_table(
+                            &["NAME", "STATE", "ENDPOINT"],
+                            connections.iter().map(VpnConnection::row).collect(),
+                        );
+                        Ok(())
+                    }
+                }
+            }
+            Query::Devices => {
+                let devices = devices(&network_manager).await?;
+                match self.json {
+                    true => print_json(&devices),
+                    false => {
+                        print_table(
+                            &["INTERFACE", "TYPE", "STATE", "CONNECTION"],
+                            devices.iter().map(DeviceInfo::row).collect(),
+                        );
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Status {
+    state: String,
+    connectivity: String,
+    networking: bool,
+    /// Left out when the machine has no radio of that kind.
+    wifi: Option<bool>,
+    wwan: Option<bool>,
+    airplane_mode: bool,
+    primary_connection: Option<String>,
+}
+
+impl Status {
+    fn rows(&self) -> Vec<Vec<String>> {
+        let on_off = |on: bool| match on {
+            true => "on".to_string(),
+            false => "off".to_string(),
+        };
+
+        let mut rows = vec![
+            vec!["State:".into(), self.state.clone()],
+            vec!["Connectivity:".into(), self.connectivity.clone()],
+            vec!["Networking:".into(), on_off(self.networking)],
+        ];
+
+        if let Some(wifi) = self.wifi {
+            rows.push(vec!["Wi-Fi:".into(), on_off(wifi)]);
+        }
+        if let Some(wwan) = self.wwan {
+            rows.push(vec!["Mobile broadband:".into(), on_off(wwan)]);
+        }
+
+        rows.push(vec!["Airplane mode:".into(), on_off(self.airplane_mode)]);
+        rows.push(vec![
+            "Primary connection:".into(),
+            self.primary_connection
+                .clone()
+                .unwrap_or_else(|| "none".into()),
+        ]);
+
+        rows
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct WifiNetwork {
+    ssid: String,
+    bssid: String,
+    strength: u8,
+    secure: bool,
+    /// Whether there's a saved connection for it.
+    known: bool,
+    in_use: bool,
+}
+
+impl WifiNetwork {
+    fn row(&self) -> Vec<String> {
+        vec![
+            match self.in_use {
+                true => "*".into(),
+                false => String::new(),
+            },
+            self.ssid.clone(),
+            format!("{}%", self.strength),
+            yes_no(self.secure),
+            yes_no(self.known),
+        ]
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct VpnConnection {
+    name: String,
+    state: String,
+    endpoint: Option<String>,
+}
+
+impl VpnConnection {
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.name.clone(),
+            self.state.clone(),
+            self.endpoint.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct DeviceInfo {
+    interface: String,
+    device_type: String,
+    state: String,
+    connection: Option<String>,
+}
+
+impl DeviceInfo {
+    fn row(&self) -> Vec<String> {
+        vec![
+            self.interface.clone(),
+            self.device_type.clone(),
+            self.state.clone(),
+            self.connection.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+async fn status(network_manager: &NetworkManager) -> Result<Status> {
+    let radio_flags = network_manager.radio_flags().await?;
+
+    let wifi = match radio_flags.contains(RadioFlags::WLAN_AVAILABLE) {
+        true => Some(network_manager.wifi_enabled().await?),
+        false => None,
+    };
+    let wwan = match radio_flags.contains(RadioFlags::WWAN_AVAILABLE) {
+        true => Some(network_manager.wwan_enabled().await?),
+        false => None,
+    };
+
+    let primary_connection = match network_manager.primary_connection().await? {
+        Some(primary_connection) => Some(primary_connection.id()?),
+        None => None,
+    };
+
+    Ok(Status {
+        state: format!("{:?}", network_manager.state().await?),
+        connectivity: tray_connectivity(network_manager.connectivity().await?)
+            .label()
+            .into(),
+        networking: network_manager.networking_enabled().await?,
+        wifi,
+        wwan,
+        airplane_mode: airplane_mode::is_enabled(),
+        primary_connection,
+    })
+}
+
+/// Lists every network in range once, by its strongest access point.
+async fn wifi_networks(network_manager: &NetworkManager) -> Result<Vec<WifiNetwork>> {
+    let mut networks = HashMap::<String, WifiNetwork>::new();
+
+    for device in network_manager.devices().await? {
+        if device.device_type()? != DeviceType::Wifi {
+            continue;
+        }
+
+        let Some(SpecificDevice::Wireless(wireless_device)) = device.to_specific_device().await
+        else {
+            continue;
+        };
+
+        let mut known = HashSet::new();
+        for connection in device.available_connections().await.unwrap_or_default() {
+            known.insert(connection.id().await?);
+        }
+
+        let active_hw_address = match wireless_device.active_access_point().await? {
+            Some(access_point) => Some(access_point.hw_address()?),
+            None => None,
+        };
+
+        for access_point in wireless_device.access_points().await? {
+            let ssid = access_point.id()?;
+            if ssid.is_empty() {
+                continue;
+            }
+
+            let bssid = access_point.hw_address()?;
+            let in_use = active_hw_address.as_ref() == Some(&bssid);
+            let strength = access_point.strength().unwrap_or_default();
+
+            let network = WifiNetwork {
+                known: known.contains(&ssid),
+                secure: access_point.secure().unwrap_or_default(),
+                ssid: ssid.clone(),
+                bssid,
+                strength,
+                in_use,
+            };
+
+            match networks.get_mut(&ssid) {
+                Some(existing) if existing.in_use || (existing.strength >= strength && !in_use) => {
+                }
+                Some(existing) => *existing = network,
+                None => {
+                    networks.insert(ssid, network);
+                }
+            }
+        }
+    }
+
+    let mut networks = networks.into_values().collect::<Vec<WifiNetwork>>();
+    networks.sort_by(|a, b| b.strength.cmp(&a.strength).then(a.ssid.cmp(&b.ssid)));
+
+    Ok(networks)
+}
+
+async fn vpn_connections(network_manager: &NetworkManager) -> Result<Vec<VpnConnection>> {
+    let mut connections = vec![];
+
+    for device in network_manager.devices().await? {
+        if device.device_type()? != DeviceType::WireGuard {
+            continue;
+        }
+
+        let Some(active_connection) = device.active_connection().await? else {
+            continue;
+        };
+
+        let endpoint = match active_connection.settings().await {
+            Ok(settings) => settings
+                .wireguard_endpoints()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .next(),
+            Err(_) => None,
+        };
+
+        connections.push(VpnConnection {
+            name: active_connection.id()?,
+            state: format!("{:?}", device.state()?),
+            endpoint,
+        });
+    }
+
+    Ok(connections)
+}
+
+async fn devices(network_manager: &NetworkManager) -> Result<Vec<DeviceInfo>> {
+    let mut devices = vec![];
+
+    for device in network_manager.devices().await? {
+        let connection = match device.active_connection().await? {
+            Some(active_connection) => Some(active_connection.id()?),
+            None => None,
+        };
+
+        devices.push(DeviceInfo {
+            interface: device.interface()?,
+            device_type: format!("{:?}", device.device_type()?),
+            state: format!("{:?}", device.state()?),
+            connection,
+        });
+    }
+
+    Ok(devices)
+}
+
+fn yes_no(value: bool) -> String {
+    match value {
+        true => "yes".into(),
+        false => "no".into(),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Prints `rows` in columns. Headers that are all empty are left out.
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect::<Vec<usize>>();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let print_row = |cells: &[&str]| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<String>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    };
+
+    if headers.iter().any(|header| !header.is_empty()) {
+        print_row(headers);
+    }
+
+    for row in &rows {
+        print_row(&row.iter().map(String::as_str).collect::<Vec<&str>>());
+    }
+}
@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeMap,
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+};
+
+use anyhow::{Result, bail};
+use log::info;
+use tokio::io::unix::AsyncFd;
+
+const RFKILL_DEVICE: &str = "/dev/rfkill";
+/// Size of the original `struct rfkill_event`. Newer kernels append fields to it, which are
+/// ignored here.
+const EVENT_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RadioType {
+    All,
+    Wlan,
+    Bluetooth,
+    Uwb,
+    Wimax,
+    Wwan,
+    Gps,
+    Fm,
+    Nfc,
+    Unknown(u8),
+}
+
+impl From<u8> for RadioType {
+    fn from(radio_type: u8) -> Self {
+        match radio_type {
+            0 => RadioType::All,
+            1 => RadioType::Wlan,
+            2 => RadioType::Bluetooth,
+            3 => RadioType::Uwb,
+            4 => RadioType::Wimax,
+            5 => RadioType::Wwan,
+            6 => RadioType::Gps,
+            7 => RadioType::Fm,
+            8 => RadioType::Nfc,
+            radio_type => RadioType::Unknown(radio_type),
+        }
+    }
+}
+
+impl From<RadioType> for u8 {
+    fn from(radio_type: RadioType) -> Self {
+        match radio_type {
+            RadioType::All => 0,
+            RadioType::Wlan => 1,
+            RadioType::Bluetooth => 2,
+            RadioType::Uwb => 3,
+            RadioType::Wimax => 4,
+            RadioType::Wwan => 5,
+            RadioType::Gps => 6,
+            RadioType::Fm => 7,
+            RadioType::Nfc => 8,
+            RadioType::Unknown(radio_type) => radio_type,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// A radio appeared. Also sent for every existing radio when the device is opened.
+    Add,
+    /// A radio went away.
+    Delete,
+    /// The block state of one radio changed, or should change when written.
+    Change,
+    /// Sets the block state of every radio of a type. Only meaningful when written.
+    ChangeAll,
+    Unknown(u8),
+}
+
+impl From<u8> for Operation {
+    fn from(operation: u8) -> Self {
+        match operation {
+            0 => Operation::Add,
+            1 => Operation::Delete,
+            2 => Operation::Change,
+            3 => Operation::ChangeAll,
+            operation => Operation::Unknown(operation),
+        }
+    }
+}
+
+impl From<Operation> for u8 {
+    fn from(operation: Operation) -> Self {
+        match operation {
+            Operation::Add => 0,
+            Operation::Delete => 1,
+            Operation::Change => 2,
+            Operation::ChangeAll => 3,
+            Operation::Unknown(operation) => operation,
+        }
+    }
+}
+
+/// `struct rfkill_event` from `linux/rfkill.h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Event {
+    pub index: u32,
+    pub radio_type: RadioType,
+    pub operation: Operation,
+    pub soft_blocked: bool,
+    pub hard_blocked: bool,
+}
+
+impl Event {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < EVENT_SIZE {
+            bail!(
+                "rfkill event is {} bytes long, expected at least {}",
+                bytes.len(),
+                EVENT_SIZE
+            );
+        }
+
+        Ok(Self {
+            index: u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            radio_type: RadioType::from(bytes[4]),
+            operation: Operation::from(bytes[5]),
+            soft_blocked: bytes[6] != 0,
+            hard_blocked: bytes[7] != 0,
+        })
+    }
+
+    pub fn to_bytes(self) -> [u8; EVENT_SIZE] {
+        let index = self.index.to_ne_bytes();
+        [
+            index[0],
+            index[1],
+            index[2],
+            index[3],
+            self.radio_type.into(),
+            self.operation.into(),
+            self.soft_blocked as u8,
+            self.hard_blocked as u8,
+        ]
+    }
+}
+
+/// A radio as last reported by the kernel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Radio {
+    pub index: u32,
+    pub radio_type: RadioType,
+    /// Blocked in software, e.g. by `rfkill block`.
+    pub soft_blocked: bool,
+    /// Blocked by a hardware switch, which software can't undo.
+    pub hard_blocked: bool,
+}
+
+/// Lists the radios known to the kernel, ordered by index.
+pub fn radios() -> Result<Vec<Radio>> {
+    let mut file = open(false)?;
+    let mut radios = BTreeMap::new();
+
+    // Opening the device queues an `Add` event for every radio; reading stops once they are
+    // drained.
+    loop {
+        let event = match read_event(&mut file) {
+            Ok(event) => event,
+            Err(e) if is_would_block(&e) => break,
+            Err(e) => return Err(e),
+        };
+
+        apply(&mut radios, event);
+    }
+
+    Ok(radios.into_values().collect())
+}
+
+/// Lists the radios of one type.
+pub fn radios_of_type(radio_type: RadioType) -> Result<Vec<Radio>> {
+    Ok(radios()?
+        .into_iter()
+        .filter(|radio| radio.radio_type == radio_type)
+        .collect())
+}
+
+/// Blocks or unblocks every radio of `radio_type` in software.
+pub fn set_blocked(radio_type: RadioType, blocked: bool) -> Result<()> {
+    write_event(Event {
+        index: 0,
+        radio_type,
+        operation: Operation::ChangeAll,
+        soft_blocked: blocked,
+        hard_blocked: false,
+    })
+}
+
+/// Blocks or unblocks a single radio in software.
+pub fn set_radio_blocked(index: u32, blocked: bool) -> Result<()> {
+    write_event(Event {
+        index,
+        radio_type: RadioType::All,
+        operation: Operation::Change,
+        soft_blocked: blocked,
+        hard_blocked: false,
+    })
+}
+
+/// Calls `f` with every change to the radios: radios being added or removed, and radios being
+/// blocked or unblocked in software or by a hardware switch.
+pub async fn listening_to_changes<F>(f: F) -> Result<()>
+where
+    F: AsyncFnOnce(Event) -> () + Send + Copy,
+{
+    let mut file = open(false)?;
+
+    // Skip the events describing the radios that already exist.
+    loop {
+        match read_event(&mut file) {
+            Ok(_) => {}
+            Err(e) if is_would_block(&e) => break,
+            Err(e) => return Err(e),
+        }
+    }
+
+    info!("Watching {} for changes", RFKILL_DEVICE);
+
+    let mut file = AsyncFd::new(file)?;
+
+    loop {
+        let mut guard = file.readable_mut().await?;
+
+        let event = match read_event(guard.get_inner_mut()) {
+            Ok(event) => event,
+            Err(e) if is_would_block(&e) => {
+                guard.clear_ready();
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+
+        drop(guard);
+
+        info!("rfkill event: {:?}", event);
+
+        f(event).await;
+    }
+}
+
+fn open(write: bool) -> Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(write)
+        .custom_flags(libc::O_NONBLOCK | libc::O_CLOEXEC)
+        .open(RFKILL_DEVICE)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", RFKILL_DEVICE, e))
+}
+
+fn read_event(file: &mut File) -> Result<Event> {
+    // Larger than any version of the event, since the kernel truncates to the size asked for.
+    let mut buffer = [0; 32];
+    let length = file.read(&mut buffer)?;
+    Event::parse(&buffer[..length])
+}
+
+fn write_event(event: Event) -> Result<()> {
+    let mut file = open(true)?;
+    let bytes = event.to_bytes();
+
+    let written = file.write(&bytes)?;
+    if written != bytes.len() {
+        bail!(
+            "Wrote {} of {} bytes to {}",
+            written,
+            bytes.len(),
+            RFKILL_DEVICE
+        );
+    }
+
+    Ok(())
+}
+
+fn apply(radios: &mut BTreeMap<u32, Radio>, event: Event) {
+    match event.operation {
+        Operation::Add | Operation::Change => {
+            radios.insert(
+                event.index,
+                Radio {
+                    index: event.index,
+                    radio_type: event.radio_type,
+                    soft_blocked: event.soft_blocked,
+                    hard_blocked: event.hard_blocked,
+                },
+            );
+        }
+        Operation::Delete => {
+            radios.remove(&event.index);
+        }
+        Operation::ChangeAll | Operation::Unknown(_) => {}
+    }
+}
+
+fn is_would_block(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == ErrorKind::WouldBlock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `struct rfkill_event` as read from the device: the index in native byte order, then the
+    /// type, operation, soft and hard bytes, then whatever newer kernels append.
+    fn event_bytes(index: u32, rest: &[u8]) -> Vec<u8> {
+        let mut bytes = index.to_ne_bytes().to_vec();
+        bytes.extend_from_slice(rest);
+        bytes
+    }
+
+    fn event(index: u32, radio_type: RadioType, operation: Operation, soft: bool) -> Event {
+        Event {
+            index,
+            radio_type,
+            operation,
+            soft_blocked: soft,
+            hard_blocked: false,
+        }
+    }
+
+    #[test]
+    fn parses_v1_event() {
+        // phy0 reported when the device is opened: Wi-Fi, added, soft blocked.
+        let bytes = event_bytes(2, &[1, 0, 1, 0]);
+
+        assert_eq!(
+            Event::parse(&bytes).unwrap(),
+            Event {
+                index: 2,
+                radio_type: RadioType::Wlan,
+                operation: Operation::Add,
+                soft_blocked: true,
+                hard_blocked: false,
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_fields_of_newer_kernels() {
+        // Linux 5.11 appends `hard_block_reasons`, here RFKILL_HARD_BLOCK_SIGNAL.
+        let bytes = event_bytes(0, &[2, 2, 0, 1, 1]);
+
+        assert_eq!(
+            Event::parse(&bytes).unwrap(),
+            Event {
+                index: 0,
+                radio_type: RadioType::Bluetooth,
+                operation: Operation::Change,
+                soft_blocked: false,
+                hard_blocked: true,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_short_event() {
+        let bytes = event_bytes(0, &[1, 0]);
+
+        assert!(Event::parse(&bytes).is_err());
+        assert!(Event::parse(&[]).is_err());
+    }
+
+    #[test]
+    fn keeps_unknown_values() {
+        let bytes = event_bytes(7, &[42, 9, 0, 0]);
+        let event = Event::parse(&bytes).unwrap();
+
+        assert_eq!(event.radio_type, RadioType::Unknown(42));
+        assert_eq!(event.operation, Operation::Unknown(9));
+    }
+
+    #[test]
+    fn round_trips_through_bytes() {
+        let event = Event {
+            index: 0x01020304,
+            radio_type: RadioType::Wwan,
+            operation: Operation::ChangeAll,
+            soft_blocked: true,
+            hard_blocked: true,
+        };
+
+        assert_eq!(Event::parse(&event.to_bytes()).unwrap(), event);
+        assert_eq!(
+            event.to_bytes().to_vec(),
+            event_bytes(0x01020304, &[5, 3, 1, 1])
+        );
+    }
+
+    #[test]
+    fn applies_events_to_radios() {
+        let mut radios = BTreeMap::new();
+
+        apply(
+            &mut radios,
+            event(1, RadioType::Wlan, Operation::Add, false),
+        );
+        apply(
+            &mut radios,
+            event(0, RadioType::Bluetooth, Operation::Add, false),
+        );
+        apply(
+            &mut radios,
+            event(0, RadioType::Bluetooth, Operation::Change, true),
+        );
+
+        assert_eq!(radios.len(), 2);
+        assert!(radios[&0].soft_blocked);
+        assert!(!radios[&1].soft_blocked);
+        // Ordered by index.
+        assert_eq!(radios.keys().copied().collect::<Vec<_>>(), [0, 1]);
+
+        apply(
+            &mut radios,
+            event(1, RadioType::Wlan, Operation::Delete, false),
+        );
+
+        assert_eq!(radios.keys().copied().collect::<Vec<_>>(), [0]);
+    }
+
+    #[test]
+    fn ignores_change_all_when_applying() {
+        let mut radios = BTreeMap::new();
+
+        apply(
+            &mut radios,
+            event(0, RadioType::Wlan, Operation::Add, false),
+        );
+        apply(
+            &mut radios,
+            event(0, RadioType::All, Operation::ChangeAll, true),
+        );
+
+        assert!(!radios[&0].soft_blocked);
+    }
+}
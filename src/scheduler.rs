@@ -0,0 +1,145 @@
+use std::time::Duration;
+
+use bitflags::bitflags;
+use tokio::time::Instant;
+
+bitflags! {
+    /// Parts of the tray that need to be recomputed on the next update.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Subsystems: u8 {
+        const ICON = 1 << 0;
+        const WIFI = 1 << 1;
+        const WIRED = 1 << 2;
+        const VPN = 1 << 3;
+        const AIRPLANE_MODE = 1 << 4;
+        const NETWORKING = 1 << 5;
+    }
+}
+
+/// Coalesces update requests so that a burst of signals results in a single update.
+///
+/// The window starts with the first request after a flush and is not extended by the ones that
+/// follow, so a steady stream of signals can't postpone the update indefinitely.
+#[derive(Debug)]
+pub struct UpdateScheduler {
+    window: Duration,
+    dirty: Subsystems,
+    deadline: Option<Instant>,
+    requests: usize,
+}
+
+impl UpdateScheduler {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            dirty: Subsystems::empty(),
+            deadline: None,
+            requests: 0,
+        }
+    }
+
+    /// Takes effect from the next window.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    pub fn schedule(&mut self, subsystems: Subsystems) {
+        self.dirty |= subsystems;
+        self.requests += 1;
+
+        if self.deadline.is_none() {
+            self.deadline = Some(Instant::now() + self.window);
+        }
+    }
+
+    /// Resolves once the pending update is due. Never resolves while nothing is pending.
+    pub async fn ready(&self) {
+        match self.deadline {
+            Some(deadline) => tokio::time::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Returns the dirty subsystems along with how many requests were merged into them.
+    pub fn take(&mut self) -> (Subsystems, usize) {
+        let pending = (self.dirty, self.requests);
+
+        self.dirty = Subsystems::empty();
+        self.deadline = None;
+        self.requests = 0;
+
+        pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOW: Duration = Duration::from_millis(250);
+
+    /// Feeds `requests` to a scheduler `interval` apart, the way `App::run` does, and returns the
+    /// updates it asked for.
+    async fn updates(requests: &[Subsystems], interval: Duration) -> Vec<(Subsystems, usize)> {
+        let mut scheduler = UpdateScheduler::new(WINDOW);
+        let mut requests = requests.iter();
+        let mut ticks = tokio::time::interval(interval);
+        let mut updates = Vec::new();
+
+        loop {
+            tokio::select! {
+                _ = ticks.tick() => match requests.next() {
+                    Some(subsystems) => scheduler.schedule(*subsystems),
+                    None => break,
+                },
+                _ = scheduler.ready() => updates.push(scheduler.take()),
+            }
+        }
+
+        if tokio::time::timeout(WINDOW, scheduler.ready())
+            .await
+            .is_ok()
+        {
+            updates.push(scheduler.take());
+        }
+
+        updates
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_coalesced_into_one_update() {
+        // A scan: 40 access points and the icon changing, 5ms apart.
+        let mut requests = vec![Subsystems::WIFI; 40];
+        requests.push(Subsystems::ICON);
+
+        let updates = updates(&requests, Duration::from_millis(5)).await;
+
+        assert_eq!(updates, [(Subsystems::WIFI | Subsystems::ICON, 41)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn steady_stream_is_not_postponed() {
+        let requests = vec![Subsystems::ICON; 100];
+
+        let updates = updates(&requests, Duration::from_millis(30)).await;
+
+        // Each window takes in the 9 requests that arrive within 250ms of its first one.
+        assert_eq!(updates.len(), 12);
+        assert!(updates.iter().all(|(_, requests)| *requests <= 9));
+        assert_eq!(
+            updates.iter().map(|(_, requests)| requests).sum::<usize>(),
+            100
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn nothing_pending_is_never_ready() {
+        let scheduler = UpdateScheduler::new(WINDOW);
+
+        assert!(
+            tokio::time::timeout(Duration::from_secs(60), scheduler.ready())
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use futures::StreamExt;
+use log::info;
+use zbus::Connection;
+
+use crate::interfaces::login_manager::ManagerProxy;
+
+/// Calls `f` with `true` right before the system suspends or hibernates and with `false` once it
+/// has resumed.
+pub async fn listening_to_sleep<F>(connection: &Connection, f: F) -> Result<()>
+where
+    F: AsyncFnOnce(bool) -> () + Send + Copy,
+{
+    let login_manager = ManagerProxy::new(connection).await?;
+    let mut stream = login_manager.receive_prepare_for_sleep().await?;
+
+    while let Some(prepare_for_sleep) = stream.next().await {
+        let start = match prepare_for_sleep.args() {
+            Ok(args) => *args.start(),
+            Err(e) => {
+                anyhow::bail!("Failed to get PrepareForSleep arguments: {e}");
+            }
+        };
+
+        info!("{}", if start { "Going to sleep" } else { "Resumed" });
+
+        f(start).await;
+    }
+
+    Ok(())
+}
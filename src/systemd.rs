@@ -0,0 +1,202 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fs, io,
+    os::{
+        linux::net::SocketAddrExt,
+        unix::{
+            ffi::OsStrExt,
+            net::{SocketAddr, UnixDatagram},
+        },
+    },
+    path::Path,
+    sync::Once,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, bail};
+use log::{info, warn};
+use tokio::time::{Interval, MissedTickBehavior};
+
+use crate::{APP_ID, config};
+
+const SERVICE_FILE: &str = "networkless.service";
+const WATCHDOG_SEC: u64 = 30;
+
+/// Whether the service manager is listening for notifications.
+pub fn is_enabled() -> bool {
+    env::var_os("NOTIFY_SOCKET").is_some()
+}
+
+/// Sends `state` to the service manager, e.g. `STATUS=Connected`. Does nothing unless the applet
+/// was started by systemd with notifications on.
+pub fn notify(state: &str) {
+    let Some(path) = env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+
+    if let Err(e) = send(&path, state) {
+        warn!("Failed to notify the service manager: {}", e);
+    }
+}
+
+/// Tells the service manager that start-up is complete. Only the first call is sent.
+pub fn ready() {
+    static READY: Once = Once::new();
+
+    READY.call_once(|| {
+        info!("Ready");
+        notify("READY=1");
+    });
+}
+
+pub fn status(status: &str) {
+    notify(&format!("STATUS={}", status));
+}
+
+fn send(path: &OsStr, state: &str) -> io::Result<()> {
+    // A leading "@" stands for a socket in the abstract namespace.
+    let address = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(path)?,
+    };
+
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to_addr(state.as_bytes(), &address)?;
+
+    Ok(())
+}
+
+/// Keeps time for `WATCHDOG=1` pings when the service manager asked for them.
+pub struct Watchdog {
+    interval: Option<Interval>,
+}
+
+impl Watchdog {
+    pub fn new() -> Self {
+        let interval = timeout().map(|timeout| {
+            info!("Watchdog enabled with a {:?} timeout", timeout);
+
+            // Pinging at half the timeout leaves room for a slow update.
+            let mut interval = tokio::time::interval(timeout / 2);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            interval
+        });
+
+        Self { interval }
+    }
+
+    /// Resolves once a ping is due. Never resolves while the watchdog is off.
+    pub async fn tick(&mut self) {
+        match &mut self.interval {
+            Some(interval) => {
+                interval.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+}
+
+/// `$WATCHDOG_USEC`, unless `$WATCHDOG_PID` names another process.
+fn timeout() -> Option<Duration> {
+    if let Some(pid) = env::var_os("WATCHDOG_PID")
+        && pid.to_str() != Some(&std::process::id().to_string())
+    {
+        return None;
+    }
+
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse::<u64>().ok()?;
+    match usec {
+        0 => None,
+        usec => Some(Duration::from_micros(usec)),
+    }
+}
+
+/// Writes a systemd user unit and an XDG autostart entry that start the applet with the session.
+pub fn install_service(force: bool) -> Result<()> {
+    let Some(config_home) = config::config_home() else {
+        bail!("Neither XDG_CONFIG_HOME nor HOME is set");
+    };
+    let exe = env::current_exe().context("Failed to find the executable")?;
+
+    let service_file = config_home.join("systemd/user").join(SERVICE_FILE);
+    let desktop_file = config_home
+        .join("autostart")
+        .join(format!("{}.desktop", APP_ID));
+
+    for path in [&service_file, &desktop_file] {
+        if path.exists() && !force {
+            bail!(
+                "{} already exists. Pass --force to replace it",
+                path.display()
+            );
+        }
+    }
+
+    write(&service_file, &service_unit(&exe))?;
+    write(&desktop_file, &desktop_entry(&exe))?;
+
+    println!("Wrote {}", service_file.display());
+    println!("Wrote {}", desktop_file.display());
+    println!();
+    println!("Start the applet with the graphical session by running:");
+    println!("  systemctl --user daemon-reload");
+    println!("  systemctl --user enable --now {}", SERVICE_FILE);
+
+    Ok(())
+}
+
+fn service_unit(exe: &Path) -> String {
+    format!(
+        "[Unit]
+Description=Networkless network applet
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+Type=notify
+NotifyAccess=main
+ExecStart={} --log journald
+Restart=on-failure
+WatchdogSec={}
+
+[Install]
+WantedBy=graphical-session.target
+",
+        quote(exe),
+        WATCHDOG_SEC
+    )
+}
+
+/// For sessions that aren't managed by systemd. Sessions that are skip it and use the unit.
+fn desktop_entry(exe: &Path) -> String {
+    format!(
+        "[Desktop Entry]
+Type=Application
+Name=Networkless
+Comment=Network manager applet
+Exec={}
+NoDisplay=true
+X-systemd-skip=true
+",
+        quote(exe)
+    )
+}
+
+/// Both unit files and desktop entries split arguments on spaces unless they are quoted.
+fn quote(path: &Path) -> String {
+    let path = path.display().to_string();
+
+    match path.contains(char::is_whitespace) {
+        true => format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\"")),
+        false => path,
+    }
+}
+
+fn write(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
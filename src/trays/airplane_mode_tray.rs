@@ -1,20 +1,30 @@
-use std::sync::LazyLock;
-
-use ksni::{Icon, Tray, menu::CheckmarkItem};
+use ksni::{
+    MenuItem, Tray,
+    menu::{CheckmarkItem, StandardItem},
+};
 
 use crate::{
     APP_ID,
     app::{Action, App},
-    trays::get_icon_from_image_bytes,
 };
 
+use super::{AirplaneModeState, icons};
+
 pub struct AirplaneModeTray {
     app: App,
+    airplane_mode_state: Option<AirplaneModeState>,
 }
 
 impl AirplaneModeTray {
     pub fn new(app: App) -> Self {
-        Self { app }
+        Self {
+            app,
+            airplane_mode_state: None,
+        }
+    }
+
+    pub fn set_airplane_mode_state(&mut self, airplane_mode_state: Option<AirplaneModeState>) {
+        self.airplane_mode_state = airplane_mode_state;
     }
 }
 
@@ -27,29 +37,64 @@ impl Tray for AirplaneModeTray {
         "Airplane Mode".into()
     }
 
-    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
-        let mut icon = Vec::with_capacity(1);
-
-        static AIRPLANE_MODE_ICON: LazyLock<Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/airplane_mode.png"))
-        });
-
-        icon.push(AIRPLANE_MODE_ICON.clone());
+    fn icon_name(&self) -> String {
+        icons::AIRPLANE_MODE.icon_name(&self.app.config().icons)
+    }
 
-        icon
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        icons::AIRPLANE_MODE.icon_pixmap()
     }
 
-    fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
-        vec![
+    fn menu(&self) -> Vec<MenuItem<Self>> {
+        let mut menu = vec![
             CheckmarkItem {
-                label: "On".into(),
-                checked: true,
+                label: "Airplane Mode".into(),
+                checked: self
+                    .airplane_mode_state
+                    .as_ref()
+                    .is_none_or(|airplane_mode_state| airplane_mode_state.on),
                 activate: Box::new(|this: &mut Self| {
                     this.app.send_action_blocking(Action::ToggleAirplaneMode);
                 }),
                 ..Default::default()
             }
             .into(),
-        ]
+        ];
+
+        let Some(airplane_mode_state) = &self.airplane_mode_state else {
+            return menu;
+        };
+
+        if airplane_mode_state.radios.is_empty() {
+            return menu;
+        }
+
+        menu.push(MenuItem::Separator);
+        menu.push(
+            StandardItem {
+                label: "Radios".into(),
+                enabled: false,
+                ..Default::default()
+            }
+            .into(),
+        );
+
+        for radio in &airplane_mode_state.radios {
+            let action = radio.kind.toggle_action();
+            menu.push(
+                CheckmarkItem {
+                    label: radio.label(),
+                    checked: radio.on,
+                    enabled: !radio.hardware_blocked,
+                    activate: Box::new(move |this: &mut Self| {
+                        this.app.send_action_blocking(action.clone());
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
+        menu
     }
 }
@@ -0,0 +1,317 @@
+use std::{
+    collections::HashMap,
+    fs,
+    sync::{LazyLock, Mutex},
+};
+
+use image::{ImageResult, RgbaImage, imageops};
+use log::{info, warn};
+
+use crate::config::{self, IconSource, IconsConfig};
+
+use super::Badges;
+
+const OVERRIDES_DIR: &str = "icons";
+
+/// Sizes every icon is rendered at so hosts can pick the one closest to the panel's scale.
+const SIZES: [u32; 6] = [16, 22, 24, 32, 48, 64];
+
+/// An icon that can be shown either by name from the desktop's icon theme or from the image
+/// embedded in the binary.
+pub struct ThemedIcon {
+    /// File name, without the `.png` extension, of the icon in the user's override directory.
+    pub key: &'static str,
+    /// Name of the icon in freedesktop icon themes.
+    pub name: &'static str,
+    pub image: LazyLock<RgbaImage>,
+}
+
+impl ThemedIcon {
+    pub fn icon_name(&self, config: &IconsConfig) -> String {
+        if self.override_image().is_some() {
+            return String::new();
+        }
+
+        match config.source {
+            IconSource::Theme => self.name.into(),
+            IconSource::Embedded => String::new(),
+        }
+    }
+
+    /// Hosts prefer `icon_name` when it is set, so the pixmap is always provided as a fallback
+    /// for themes that lack the icon.
+    pub fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        self.icon_pixmap_with_badges(Badges::empty())
+    }
+
+    /// Renders the icon with `badges` drawn over it. Themes have no names for such combinations,
+    /// so these are only ever shown as pixmaps.
+    pub fn icon_pixmap_with_badges(&self, badges: Badges) -> Vec<ksni::Icon> {
+        if let Some(rendered) = RENDERED.lock().unwrap().get(&(self.key, badges)) {
+            return rendered.clone();
+        }
+
+        let base = self.override_image().unwrap_or_else(|| self.image.clone());
+        let badge_images = badges
+            .iter()
+            .map(|badge| {
+                let badge = badge_icon(badge);
+                badge
+                    .override_image()
+                    .unwrap_or_else(|| badge.image.clone())
+            })
+            .collect::<Vec<RgbaImage>>();
+
+        let rendered = SIZES
+            .iter()
+            .map(|size| {
+                let mut canvas = resize(&base, *size);
+                for badge in &badge_images {
+                    imageops::overlay(&mut canvas, &resize(badge, *size), 0, 0);
+                }
+                icon_from_image(canvas)
+            })
+            .collect::<Vec<ksni::Icon>>();
+
+        RENDERED
+            .lock()
+            .unwrap()
+            .insert((self.key, badges), rendered.clone());
+
+        rendered
+    }
+
+    fn override_image(&self) -> Option<RgbaImage> {
+        OVERRIDES
+            .lock()
+            .unwrap()
+            .entry(self.key)
+            .or_insert_with(|| load_override(self.key))
+            .clone()
+    }
+}
+
+static OVERRIDES: LazyLock<Mutex<HashMap<&'static str, Option<RgbaImage>>>> =
+    LazyLock::new(Default::default);
+
+type RenderKey = (&'static str, Badges);
+
+static RENDERED: LazyLock<Mutex<HashMap<RenderKey, Vec<ksni::Icon>>>> =
+    LazyLock::new(Default::default);
+
+fn load_override(key: &str) -> Option<RgbaImage> {
+    let path = config::config_dir()?
+        .join(OVERRIDES_DIR)
+        .join(format!("{key}.png"));
+
+    let bytes = fs::read(&path).ok()?;
+
+    match try_load_image(&bytes) {
+        Ok(image) => {
+            info!("Using {} for the {} icon", path.display(), key);
+            Some(image)
+        }
+        Err(e) => {
+            warn!("Ignoring {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Forgets the override icons loaded and the icons rendered so far so that they are read and
+/// rendered again on next use.
+pub fn clear_cache() {
+    OVERRIDES.lock().unwrap().clear();
+    RENDERED.lock().unwrap().clear();
+}
+
+fn resize(image: &RgbaImage, size: u32) -> RgbaImage {
+    if image.dimensions() == (size, size) {
+        return image.clone();
+    }
+
+    imageops::resize(image, size, size, imageops::FilterType::Lanczos3)
+}
+
+fn try_load_image(image_bytes: &[u8]) -> ImageResult<RgbaImage> {
+    let image = image::load_from_memory_with_format(image_bytes, image::ImageFormat::Png)?;
+    Ok(image.into_rgba8())
+}
+
+fn load_image(image_bytes: &[u8]) -> RgbaImage {
+    try_load_image(image_bytes).expect("valid image")
+}
+
+fn icon_from_image(image: RgbaImage) -> ksni::Icon {
+    let (width, height) = image.dimensions();
+    let mut data = image.into_vec();
+    assert_eq!(data.len() % 4, 0);
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.rotate_right(1) // rgba to argb
+    }
+    ksni::Icon {
+        width: width as i32,
+        height: height as i32,
+        data,
+    }
+}
+
+fn badge_icon(badge: Badges) -> &'static ThemedIcon {
+    match badge {
+        Badges::VPN => &BADGE_VPN,
+        Badges::METERED => &BADGE_METERED,
+        Badges::LIMITED => &BADGE_LIMITED,
+        Badges::CAPTIVE_PORTAL => &BADGE_CAPTIVE_PORTAL,
+        Badges::IPV6_ONLY => &BADGE_IPV6_ONLY,
+        _ => unreachable!("badges are iterated one flag at a time"),
+    }
+}
+
+pub static NOT_RUNNING: ThemedIcon = ThemedIcon {
+    key: "not-running",
+    name: "network-error-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/not-running.png"))),
+};
+
+pub static UNKNOWN: ThemedIcon = ThemedIcon {
+    key: "unknown",
+    name: "network-no-route-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/unknown.png"))),
+};
+
+pub static OFF: ThemedIcon = ThemedIcon {
+    key: "off",
+    name: "network-wireless-disabled-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-off.png"))),
+};
+
+/// Frames of the icon shown while a connection is being activated. Themes only have a still
+/// icon for this, so it is only animated when shown from pixmaps.
+pub static BUSY_FRAMES: [ThemedIcon; 3] = [
+    ThemedIcon {
+        key: "busy-1",
+        name: "network-wireless-acquiring-symbolic",
+        image: LazyLock::new(|| load_image(include_bytes!("../../assets/busy-1.png"))),
+    },
+    ThemedIcon {
+        key: "busy-2",
+        name: "network-wireless-acquiring-symbolic",
+        image: LazyLock::new(|| load_image(include_bytes!("../../assets/busy-2.png"))),
+    },
+    ThemedIcon {
+        key: "busy-3",
+        name: "network-wireless-acquiring-symbolic",
+        image: LazyLock::new(|| load_image(include_bytes!("../../assets/busy-3.png"))),
+    },
+];
+
+pub static HARDWARE_DISABLED: ThemedIcon = ThemedIcon {
+    key: "hardware-disabled",
+    name: "network-wireless-hardware-disabled-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/hardware-disabled.png"))),
+};
+
+pub static DISCONNECTED: ThemedIcon = ThemedIcon {
+    key: "disconnected",
+    name: "network-offline-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/disconnected.png"))),
+};
+
+pub static LIMITED: ThemedIcon = ThemedIcon {
+    key: "limited",
+    name: "network-wireless-no-route-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/limited.png"))),
+};
+
+pub static TUN: ThemedIcon = ThemedIcon {
+    key: "tun",
+    name: "network-vpn-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/vpn.png"))),
+};
+
+pub static ETHERNET: ThemedIcon = ThemedIcon {
+    key: "ethernet",
+    name: "network-wired-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/ethernet.png"))),
+};
+
+pub static CELLULAR: ThemedIcon = ThemedIcon {
+    key: "cellular",
+    name: "network-cellular-connected-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/cellular.png"))),
+};
+
+pub static WIFI_0: ThemedIcon = ThemedIcon {
+    key: "wifi-0",
+    name: "network-wireless-signal-none-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-0.png"))),
+};
+
+pub static WIFI_25: ThemedIcon = ThemedIcon {
+    key: "wifi-25",
+    name: "network-wireless-signal-weak-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-25.png"))),
+};
+
+pub static WIFI_50: ThemedIcon = ThemedIcon {
+    key: "wifi-50",
+    name: "network-wireless-signal-ok-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-50.png"))),
+};
+
+pub static WIFI_75: ThemedIcon = ThemedIcon {
+    key: "wifi-75",
+    name: "network-wireless-signal-good-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-75.png"))),
+};
+
+pub static WIFI_100: ThemedIcon = ThemedIcon {
+    key: "wifi-100",
+    name: "network-wireless-signal-excellent-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/wifi-100.png"))),
+};
+
+pub static VPN: ThemedIcon = ThemedIcon {
+    key: "vpn",
+    name: "network-vpn-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/virtual-vpn.png"))),
+};
+
+pub static AIRPLANE_MODE: ThemedIcon = ThemedIcon {
+    key: "airplane-mode",
+    name: "airplane-mode-symbolic",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/airplane_mode.png"))),
+};
+
+// Badges are full-size images with the badge drawn in its own corner, so that several of them
+// can be laid over the same icon. They are never looked up in the icon theme.
+
+static BADGE_VPN: ThemedIcon = ThemedIcon {
+    key: "badge-vpn",
+    name: "",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/badge-vpn.png"))),
+};
+
+static BADGE_METERED: ThemedIcon = ThemedIcon {
+    key: "badge-metered",
+    name: "",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/badge-metered.png"))),
+};
+
+static BADGE_LIMITED: ThemedIcon = ThemedIcon {
+    key: "badge-limited",
+    name: "",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/badge-limited.png"))),
+};
+
+static BADGE_CAPTIVE_PORTAL: ThemedIcon = ThemedIcon {
+    key: "badge-captive-portal",
+    name: "",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/badge-captive-portal.png"))),
+};
+
+static BADGE_IPV6_ONLY: ThemedIcon = ThemedIcon {
+    key: "badge-ipv6-only",
+    name: "",
+    image: LazyLock::new(|| load_image(include_bytes!("../../assets/badge-ipv6-only.png"))),
+};
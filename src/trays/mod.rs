@@ -1,18 +1,28 @@
+use std::time::Duration;
+
 use airplane_mode_tray::AirplaneModeTray;
-use image::GenericImageView;
 use ksni::{Handle, TrayMethods};
-use log::error;
+use log::{error, warn};
 use network_tray::NetworkTray;
+use status_notifier::listening_to_status_notifier_hosts;
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
 use vpn_tray::VpnTray;
 
-use crate::app::App;
+use crate::app::{App, Event};
 
 mod airplane_mode_tray;
+mod icons;
 mod network_tray;
+mod status_bar;
+mod status_notifier;
 mod vpn_tray;
+pub use icons::clear_cache as clear_icon_cache;
 pub use network_tray::{
-    AirplaneModeState, Icon, VPNConnection, VPNState, WifiConnection, WifiState, WiredState,
+    ActivationStage, AirplaneModeState, Badges, Base, Connectivity, ConnectivityState, Icon,
+    NetworkingState, PrimaryConnectionState, RadioKind, RadioState, VPNConnection, VPNState,
+    WifiActivation, WifiConnection, WifiState, WiredState,
 };
+pub use status_bar::{OutputFormat, StatusBar};
 
 pub enum TrayUpdate {
     Icon(Icon),
@@ -20,62 +30,187 @@ pub enum TrayUpdate {
     Wired(Option<WiredState>),
     Vpn(Option<VPNState>),
     AirplaneMode(Option<AirplaneModeState>),
+    Networking(Option<NetworkingState>),
+    PrimaryConnection(Option<PrimaryConnectionState>),
+    Connectivity(Option<ConnectivityState>),
 }
 
-pub struct TrayManager {
+const FRAME_INTERVAL: Duration = Duration::from_millis(400);
+
+/// Shows the state `App` works out, whether as tray icons or some other way.
+pub trait TrayManager {
+    async fn update(&mut self, update: TrayUpdate);
+
+    /// Shows the current state again, e.g. after a StatusNotifierHost registers or the config
+    /// changes.
+    async fn refresh(&mut self);
+
+    /// Called once all the updates of a pass have been made, for managers that would rather show
+    /// the result than every step towards it.
+    async fn flush(&mut self) {}
+
+    /// Whether the state is being shown, which is when start-up counts as complete.
+    fn is_ready(&self) -> bool {
+        true
+    }
+}
+
+/// Shows nothing, for running only for the notifications, connectivity checks and logging.
+pub struct NullTrayManager;
+
+impl TrayManager for NullTrayManager {
+    async fn update(&mut self, _update: TrayUpdate) {}
+
+    async fn refresh(&mut self) {}
+}
+
+/// Shows the state as StatusNotifierItems.
+pub struct KsniTrayManager {
     app: App,
+    status_notifier_handle: JoinHandle<()>,
     network_tray_handle: Option<Handle<NetworkTray>>,
+    animation_handle: Option<JoinHandle<()>>,
     vpn_tray_handle: Option<Handle<VpnTray>>,
     airplane_mode_tray_handle: Option<Handle<AirplaneModeTray>>,
+    /// Set when a tray couldn't be created, which happens while no StatusNotifierHost is around.
+    /// No tray is created again until one registers.
+    waiting_for_host: bool,
 }
 
-impl TrayManager {
+impl KsniTrayManager {
     pub fn new(app: App) -> Self {
+        let status_notifier_app = app.clone();
+        let status_notifier_handle = tokio::spawn(async move {
+            let app = status_notifier_app;
+            if let Err(e) = listening_to_status_notifier_hosts(async || {
+                app.send_event(Event::StatusNotifierHostRegistered).await;
+            })
+            .await
+            {
+                error!("Failed to listen to StatusNotifierHosts: {}", e);
+            }
+        });
+
         Self {
             app,
+            status_notifier_handle,
             network_tray_handle: None,
+            animation_handle: None,
             vpn_tray_handle: None,
             airplane_mode_tray_handle: None,
+            waiting_for_host: false,
         }
     }
+}
+
+impl Drop for KsniTrayManager {
+    fn drop(&mut self) {
+        self.status_notifier_handle.abort();
+    }
+}
 
-    pub async fn update(&mut self, state: TrayUpdate) {
+impl TrayManager for KsniTrayManager {
+    async fn update(&mut self, state: TrayUpdate) {
         match state {
             TrayUpdate::Icon(icon) => self.update_icon(icon).await,
             TrayUpdate::Wireless(state) => self.update_wireless(state).await,
             TrayUpdate::Wired(state) => self.update_wired(state).await,
             TrayUpdate::Vpn(state) => self.update_vpn(state).await,
             TrayUpdate::AirplaneMode(state) => self.update_airplane_mode(state).await,
+            TrayUpdate::Networking(state) => self.update_networking(state).await,
+            TrayUpdate::PrimaryConnection(state) => self.update_primary_connection(state).await,
+            TrayUpdate::Connectivity(state) => self.update_connectivity(state).await,
         };
     }
 
+    /// Not until the network tray is registered, which waits for a StatusNotifierHost.
+    fn is_ready(&self) -> bool {
+        self.network_tray_handle.is_some()
+    }
+
+    /// Drops handles whose tray service has shut down so that the next update spawns them again,
+    /// and pokes the remaining ones so a newly registered host picks up their current state.
+    async fn refresh(&mut self) {
+        self.waiting_for_host = false;
+
+        if self
+            .network_tray_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_closed())
+        {
+            self.network_tray_handle = None;
+        }
+
+        if self
+            .vpn_tray_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_closed())
+        {
+            self.vpn_tray_handle = None;
+        }
+
+        if self
+            .airplane_mode_tray_handle
+            .as_ref()
+            .is_some_and(|handle| handle.is_closed())
+        {
+            self.airplane_mode_tray_handle = None;
+        }
+
+        if let Some(network_tray_handle) = &self.network_tray_handle {
+            network_tray_handle.update(|_| {}).await;
+        }
+
+        if let Some(vpn_tray_handle) = &self.vpn_tray_handle {
+            vpn_tray_handle.update(|_| {}).await;
+        }
+
+        if let Some(airplane_mode_tray_handle) = &self.airplane_mode_tray_handle {
+            airplane_mode_tray_handle.update(|_| {}).await;
+        }
+    }
+}
+
+impl KsniTrayManager {
     async fn create_network_tray(&mut self) {
-        if self.network_tray_handle.is_some() {
+        if self.network_tray_handle.is_some() || self.waiting_for_host {
             return;
         }
         let network_tray = NetworkTray::new(self.app.clone());
         match network_tray.spawn().await {
             Ok(handle) => self.network_tray_handle = Some(handle),
-            Err(e) => error!("Error creating network tray: {:?}", e),
+            Err(e) => self.wait_for_host("network", e),
         };
     }
 
     async fn create_vpn_tray(&mut self) {
-        if self.vpn_tray_handle.is_some() {
+        if self.vpn_tray_handle.is_some() || self.waiting_for_host {
             return;
         }
         let vpn_tray = VpnTray::new(self.app.clone());
-        let handle = vpn_tray.spawn().await.unwrap();
-        self.vpn_tray_handle = Some(handle);
+        match vpn_tray.spawn().await {
+            Ok(handle) => self.vpn_tray_handle = Some(handle),
+            Err(e) => self.wait_for_host("vpn", e),
+        };
     }
 
     async fn create_airplane_mode_tray(&mut self) {
-        if self.airplane_mode_tray_handle.is_some() {
+        if self.airplane_mode_tray_handle.is_some() || self.waiting_for_host {
             return;
         }
         let airplane_mode_tray = AirplaneModeTray::new(self.app.clone());
-        let handle = airplane_mode_tray.spawn().await.unwrap();
-        self.airplane_mode_tray_handle = Some(handle);
+        match airplane_mode_tray.spawn().await {
+            Ok(handle) => self.airplane_mode_tray_handle = Some(handle),
+            Err(e) => self.wait_for_host("airplane mode", e),
+        };
+    }
+
+    fn wait_for_host(&mut self, tray: &str, e: ksni::Error) {
+        warn!(
+            "Failed to create the {} tray, waiting for a StatusNotifierHost: {:?}",
+            tray, e
+        );
+        self.waiting_for_host = true;
     }
 
     async fn update_icon(&mut self, icon: Icon) {
@@ -83,6 +218,8 @@ impl TrayManager {
             self.create_network_tray().await;
         }
 
+        let animated = matches!(icon, Icon::Busy);
+
         if let Some(network_tray_handle) = &mut self.network_tray_handle {
             network_tray_handle
                 .update(|tray| {
@@ -90,6 +227,50 @@ impl TrayManager {
                 })
                 .await;
         }
+
+        if animated {
+            self.start_animation();
+        } else {
+            self.stop_animation();
+        }
+    }
+
+    fn start_animation(&mut self) {
+        if self
+            .animation_handle
+            .as_ref()
+            .is_some_and(|handle| !handle.is_finished())
+        {
+            return;
+        }
+
+        let Some(network_tray_handle) = self.network_tray_handle.clone() else {
+            return;
+        };
+
+        self.animation_handle = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FRAME_INTERVAL);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+            loop {
+                interval.tick().await;
+
+                // The tray is gone, a new animation is started along with its replacement.
+                if network_tray_handle
+                    .update(|tray| tray.next_frame())
+                    .await
+                    .is_none()
+                {
+                    break;
+                }
+            }
+        }));
+    }
+
+    fn stop_animation(&mut self) {
+        if let Some(animation_handle) = self.animation_handle.take() {
+            animation_handle.abort();
+        }
     }
 
     async fn update_wireless(&mut self, state: Option<WifiState>) {
@@ -133,6 +314,9 @@ impl TrayManager {
                 .await;
         }
 
+        // The VPN connections are still listed in the network tray's menu.
+        let state = state.filter(|_| self.app.config().trays.vpn);
+
         if state.is_none() && self.vpn_tray_handle.is_none() {
             return;
         }
@@ -156,6 +340,48 @@ impl TrayManager {
         }
     }
 
+    async fn update_primary_connection(&mut self, state: Option<PrimaryConnectionState>) {
+        if self.network_tray_handle.is_none() {
+            self.create_network_tray().await;
+        }
+
+        if let Some(network_tray_handle) = &mut self.network_tray_handle {
+            network_tray_handle
+                .update(|tray| {
+                    tray.set_primary_connection_state(state);
+                })
+                .await;
+        }
+    }
+
+    async fn update_connectivity(&mut self, state: Option<ConnectivityState>) {
+        if self.network_tray_handle.is_none() {
+            self.create_network_tray().await;
+        }
+
+        if let Some(network_tray_handle) = &mut self.network_tray_handle {
+            network_tray_handle
+                .update(|tray| {
+                    tray.set_connectivity_state(state);
+                })
+                .await;
+        }
+    }
+
+    async fn update_networking(&mut self, state: Option<NetworkingState>) {
+        if self.network_tray_handle.is_none() {
+            self.create_network_tray().await;
+        }
+
+        if let Some(network_tray_handle) = &mut self.network_tray_handle {
+            network_tray_handle
+                .update(|tray| {
+                    tray.set_networking_state(state);
+                })
+                .await;
+        }
+    }
+
     async fn update_airplane_mode(&mut self, state: Option<AirplaneModeState>) {
         if self.network_tray_handle.is_none() {
             self.create_network_tray().await;
@@ -169,11 +395,20 @@ impl TrayManager {
                 .await;
         }
 
-        if let Some(state) = state {
-            if state.on {
-                self.create_airplane_mode_tray().await;
-                return;
+        if let Some(state) = &state
+            && state.on
+            && self.app.config().trays.airplane_mode
+        {
+            self.create_airplane_mode_tray().await;
+
+            if let Some(airplane_mode_tray_handle) = &self.airplane_mode_tray_handle {
+                airplane_mode_tray_handle
+                    .update(|tray| {
+                        tray.set_airplane_mode_state(Some(state.clone()));
+                    })
+                    .await;
             }
+            return;
         }
 
         if self.airplane_mode_tray_handle.is_some() {
@@ -183,18 +418,9 @@ impl TrayManager {
     }
 }
 
-fn get_icon_from_image_bytes(image_bytes: &[u8]) -> ksni::Icon {
-    let img = image::load_from_memory_with_format(image_bytes, image::ImageFormat::Png)
-        .expect("valid image");
-    let (width, height) = img.dimensions();
-    let mut data = img.into_rgba8().into_vec();
-    assert_eq!(data.len() % 4, 0);
-    for pixel in data.chunks_exact_mut(4) {
-        pixel.rotate_right(1) // rgba to argb
-    }
-    ksni::Icon {
-        width: width as i32,
-        height: height as i32,
-        data,
-    }
+/// Tooltips may contain markup, so text coming from the network has to be escaped.
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
 }
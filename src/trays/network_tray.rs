@@ -1,23 +1,70 @@
-use std::sync::LazyLock;
+use std::time::SystemTime;
 
+use bitflags::bitflags;
 use ksni::MenuItem;
 
 use crate::{
     APP_ID,
     app::{Action, App},
-    trays::get_icon_from_image_bytes,
+};
+
+use super::{
+    escape_markup,
+    icons::{self, ThemedIcon},
 };
 
 #[derive(Debug, Clone)]
 pub enum Icon {
+    NotRunning,
     Unknown,
     Off,
+    /// A hardware switch has the Wi-Fi radio turned off.
+    HardwareDisabled,
     Busy,
     Disconnected,
     Limited,
-    Tun,
-    Ethernet,
+    Connected {
+        base: Base,
+        badges: Badges,
+    },
+}
+
+impl Icon {
+    /// A short name for the kind of state shown, for status bars to style by.
+    pub fn class(&self) -> &'static str {
+        match self {
+            Icon::NotRunning => "not-running",
+            Icon::Unknown => "unknown",
+            Icon::Off => "off",
+            Icon::HardwareDisabled => "hardware-disabled",
+            Icon::Busy => "busy",
+            Icon::Disconnected => "disconnected",
+            Icon::Limited => "limited",
+            Icon::Connected { .. } => "connected",
+        }
+    }
+}
+
+/// The kind of connection the traffic goes through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
     Wifi(u8),
+    Ethernet,
+    Cellular,
+    /// A VPN that isn't carried by any other connection we know of.
+    Tun,
+}
+
+bitflags! {
+    /// Conditions drawn over the base of a connected icon.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    pub struct Badges: u8 {
+        const VPN = 1 << 0;
+        const METERED = 1 << 1;
+        const LIMITED = 1 << 2;
+        const CAPTIVE_PORTAL = 1 << 3;
+        const IPV6_ONLY = 1 << 4;
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -28,23 +75,104 @@ pub struct WifiConnection {
     pub secure: bool,
 }
 
+/// How far along the activation of a connection is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivationStage {
+    Prepare,
+    Config,
+    NeedAuth,
+    IpConfig,
+    IpCheck,
+}
+
+impl ActivationStage {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ActivationStage::Prepare => "Preparing…",
+            ActivationStage::Config => "Configuring…",
+            ActivationStage::NeedAuth => "Waiting for authentication…",
+            ActivationStage::IpConfig => "Getting an IP address…",
+            ActivationStage::IpCheck => "Checking connection…",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WifiActivation {
+    pub ssid: String,
+    pub stage: ActivationStage,
+}
+
 #[derive(Debug, Clone)]
 pub struct WifiState {
     pub on: bool,
+    pub active_connection: Option<WifiConnection>,
     pub active_connection_index: usize,
     pub available_connections: Vec<WifiConnection>,
     pub known_connections: Vec<WifiConnection>,
+    pub activation: Option<WifiActivation>,
+    /// Why the last attempt to connect failed.
+    pub failure: Option<String>,
+    /// Whether a hardware switch has the radio turned off, which software can't undo.
+    pub hardware_blocked: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct WiredState {
     pub on: bool,
+    pub stage: Option<ActivationStage>,
+    /// Why the last attempt to connect failed.
+    pub failure: Option<String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct VPNConnection {
     pub name: String,
     pub on: bool,
+    pub stage: Option<ActivationStage>,
+    pub endpoint: Option<String>,
+    /// When the connection was first seen activated.
+    pub connected_since: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PrimaryConnectionState {
+    pub id: String,
+    pub addresses: Vec<String>,
+    /// Speed of the link in Mb/s.
+    pub speed: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    None,
+    Portal,
+    Limited,
+    Full,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectivityState {
+    pub connectivity: Connectivity,
+    /// Connectivity of the primary connection's device over each IP version.
+    pub ip4: Option<Connectivity>,
+    pub ip6: Option<Connectivity>,
+    /// Whether NetworkManager is configured with a URI to check connectivity against.
+    pub check_available: bool,
+    pub check_enabled: bool,
+}
+
+impl Connectivity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Connectivity::None => "No connection",
+            Connectivity::Portal => "Sign-in required",
+            Connectivity::Limited => "Limited",
+            Connectivity::Full => "Full",
+            Connectivity::Unknown => "Unknown",
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -55,27 +183,84 @@ pub struct VPNState {
 #[derive(Debug, Clone)]
 pub struct AirplaneModeState {
     pub on: bool,
+    /// The radios the machine has, in the order they are listed.
+    pub radios: Vec<RadioState>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RadioKind {
+    Wifi,
+    Bluetooth,
+    Wwan,
+}
+
+impl RadioKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RadioKind::Wifi => "Wi-Fi",
+            RadioKind::Bluetooth => "Bluetooth",
+            RadioKind::Wwan => "Mobile Broadband",
+        }
+    }
+
+    pub fn toggle_action(&self) -> Action {
+        match self {
+            RadioKind::Wifi => Action::ToggleWifi,
+            RadioKind::Bluetooth => Action::ToggleBluetooth,
+            RadioKind::Wwan => Action::ToggleWwan,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RadioState {
+    pub kind: RadioKind,
+    pub on: bool,
+    /// Whether a hardware switch has the radio turned off, which software can't undo.
+    pub hardware_blocked: bool,
+}
+
+impl RadioState {
+    pub fn label(&self) -> String {
+        match self.hardware_blocked {
+            true => format!("{} (Disabled by hardware switch)", self.kind.label()),
+            false => self.kind.label().into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NetworkingState {
+    pub on: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct NetworkTray {
     app: App,
     icon: Option<Icon>,
+    frame: usize,
     pub wifi_state: Option<WifiState>,
     pub wired_state: Option<WiredState>,
     pub vpn_state: Option<VPNState>,
     pub airplane_mode_state: Option<AirplaneModeState>,
+    pub networking_state: Option<NetworkingState>,
+    pub primary_connection_state: Option<PrimaryConnectionState>,
+    pub connectivity_state: Option<ConnectivityState>,
 }
 
 impl NetworkTray {
     pub fn new(app: App) -> Self {
         Self {
             icon: None,
+            frame: 0,
             app,
             wifi_state: None,
             wired_state: None,
             vpn_state: None,
             airplane_mode_state: None,
+            networking_state: None,
+            primary_connection_state: None,
+            connectivity_state: None,
         }
     }
 
@@ -83,6 +268,11 @@ impl NetworkTray {
         self.icon = Some(icon);
     }
 
+    /// Advances the animation of the current icon, if it has one.
+    pub fn next_frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
     pub fn set_wifi_state(&mut self, wifi_state: Option<WifiState>) {
         self.wifi_state = wifi_state;
     }
@@ -95,104 +285,207 @@ impl NetworkTray {
         self.airplane_mode_state = airplane_mode_state;
     }
 
+    pub fn set_networking_state(&mut self, networking_state: Option<NetworkingState>) {
+        self.networking_state = networking_state;
+    }
+
     pub fn set_vpn_state(&mut self, vpn_state: Option<VPNState>) {
         self.vpn_state = vpn_state;
     }
-}
 
-impl ksni::Tray for NetworkTray {
-    fn id(&self) -> String {
-        APP_ID.into()
+    pub fn set_primary_connection_state(
+        &mut self,
+        primary_connection_state: Option<PrimaryConnectionState>,
+    ) {
+        self.primary_connection_state = primary_connection_state;
     }
 
-    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
-        let mut icons = Vec::with_capacity(1);
-
-        static UNKNOWN_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/unknown.png")));
-
-        static OFF_ICON: LazyLock<ksni::Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/wifi-off.png"))
-        });
-
-        static BUSY_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/busy.png")));
-
-        static DISCONNECTED_ICON: LazyLock<ksni::Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/disconnected.png"))
-        });
-
-        static LIMITED_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/limited.png")));
+    pub fn set_connectivity_state(&mut self, connectivity_state: Option<ConnectivityState>) {
+        self.connectivity_state = connectivity_state;
+    }
+}
 
-        static VPN_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/vpn.png")));
+impl NetworkTray {
+    pub fn icon(&self) -> Option<&Icon> {
+        self.icon.as_ref()
+    }
 
-        static ETHERNET_ICON: LazyLock<ksni::Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/ethernet.png"))
-        });
+    /// Name of the current icon in the user's override directory, e.g. `wifi-75`.
+    pub fn icon_key(&self) -> Option<&'static str> {
+        self.themed_icon().map(|icon| icon.key)
+    }
 
-        static WIFI_100_ICON: LazyLock<ksni::Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/wifi-100.png"))
-        });
+    fn badges(&self) -> Badges {
+        match self.icon {
+            Some(Icon::Connected { badges, .. }) => badges,
+            _ => Badges::empty(),
+        }
+    }
 
-        static WIFI_75_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/wifi-75.png")));
+    fn themed_icon(&self) -> Option<&'static ThemedIcon> {
+        let icon = match self.icon.as_ref()? {
+            Icon::NotRunning => &icons::NOT_RUNNING,
+            Icon::Unknown => &icons::UNKNOWN,
+            Icon::Off => &icons::OFF,
+            Icon::HardwareDisabled => &icons::HARDWARE_DISABLED,
+            Icon::Busy => &icons::BUSY_FRAMES[self.frame % icons::BUSY_FRAMES.len()],
+            Icon::Disconnected => &icons::DISCONNECTED,
+            Icon::Limited => &icons::LIMITED,
+            Icon::Connected { base, .. } => match base {
+                Base::Tun => &icons::TUN,
+                Base::Ethernet => &icons::ETHERNET,
+                Base::Cellular => &icons::CELLULAR,
+                Base::Wifi(strength) => match self.app.config().wifi.signal_level(*strength) {
+                    0 => &icons::WIFI_0,
+                    1 => &icons::WIFI_25,
+                    2 => &icons::WIFI_50,
+                    3 => &icons::WIFI_75,
+                    _ => &icons::WIFI_100,
+                },
+            },
+        };
 
-        static WIFI_50_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/wifi-50.png")));
+        Some(icon)
+    }
+}
 
-        static WIFI_25_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/wifi-25.png")));
+impl ksni::Tray for NetworkTray {
+    fn id(&self) -> String {
+        APP_ID.into()
+    }
 
-        static WIFI_0_ICON: LazyLock<ksni::Icon> =
-            LazyLock::new(|| get_icon_from_image_bytes(include_bytes!("../../assets/wifi-0.png")));
+    fn icon_name(&self) -> String {
+        if !self.badges().is_empty() {
+            return String::new();
+        }
 
-        match self.icon {
-            Some(Icon::Unknown) => icons.push(UNKNOWN_ICON.clone()),
-            Some(Icon::Off) => icons.push(OFF_ICON.clone()),
-            Some(Icon::Busy) => icons.push(BUSY_ICON.clone()),
-            Some(Icon::Disconnected) => icons.push(DISCONNECTED_ICON.clone()),
-            Some(Icon::Limited) => icons.push(LIMITED_ICON.clone()),
-            Some(Icon::Tun) => icons.push(VPN_ICON.clone()),
-            Some(Icon::Ethernet) => icons.push(ETHERNET_ICON.clone()),
-            Some(Icon::Wifi(0..=19)) => icons.push(WIFI_0_ICON.clone()),
-            Some(Icon::Wifi(20..=39)) => icons.push(WIFI_25_ICON.clone()),
-            Some(Icon::Wifi(40..=49)) => icons.push(WIFI_50_ICON.clone()),
-            Some(Icon::Wifi(50..=79)) => icons.push(WIFI_75_ICON.clone()),
-            Some(Icon::Wifi(80..=100)) => icons.push(WIFI_100_ICON.clone()),
-            Some(Icon::Wifi(_)) => unreachable!(),
-            None => {}
-        };
+        match self.themed_icon() {
+            Some(icon) => icon.icon_name(&self.app.config().icons),
+            None => String::new(),
+        }
+    }
 
-        icons
+    fn icon_pixmap(&self) -> Vec<ksni::Icon> {
+        match self.themed_icon() {
+            Some(icon) => icon.icon_pixmap_with_badges(self.badges()),
+            None => vec![],
+        }
     }
 
     fn title(&self) -> String {
         match self.icon {
+            Some(Icon::NotRunning) => "NetworkManager not running".into(),
             Some(Icon::Unknown) => "Unknown".into(),
             Some(Icon::Off) => "Off".into(),
+            Some(Icon::HardwareDisabled) => "Disabled by hardware switch".into(),
             Some(Icon::Busy) => "Busy".into(),
             Some(Icon::Disconnected) => "Disconnected".into(),
             Some(Icon::Limited) => "Limited".into(),
-            Some(Icon::Tun) => "VPN".into(),
-            Some(Icon::Ethernet) => "Ethernet".into(),
-            Some(Icon::Wifi(_)) => "Wifi".into(),
+            Some(Icon::Connected { base, .. }) => match base {
+                Base::Tun => "VPN".into(),
+                Base::Ethernet => "Ethernet".into(),
+                Base::Cellular => "Cellular".into(),
+                Base::Wifi(_) => "Wifi".into(),
+            },
             None => "Wireless".into(),
         }
     }
 
+    fn tool_tip(&self) -> ksni::ToolTip {
+        let title = match &self.primary_connection_state {
+            Some(primary_connection_state) => primary_connection_state.id.clone(),
+            None => self.title(),
+        };
+
+        let mut lines = vec![];
+
+        if let Some(active_connection) = self
+            .wifi_state
+            .as_ref()
+            .and_then(|wifi_state| wifi_state.active_connection.as_ref())
+        {
+            lines.push(format!(
+                "Wi-Fi: {} ({}%)",
+                active_connection.ssid, active_connection.strength
+            ));
+        }
+
+        if let Some(primary_connection_state) = &self.primary_connection_state {
+            if !primary_connection_state.addresses.is_empty() {
+                lines.push(format!(
+                    "IP address: {}",
+                    primary_connection_state.addresses.join(", ")
+                ));
+            }
+
+            if let Some(speed) = primary_connection_state.speed {
+                lines.push(format!("Speed: {} Mb/s", speed));
+            }
+        }
+
+        if let Some(vpn_state) = &self.vpn_state {
+            let connected = vpn_state
+                .connections
+                .iter()
+                .filter(|connection| connection.on)
+                .map(|connection| connection.name.as_str())
+                .collect::<Vec<&str>>();
+
+            match connected.is_empty() {
+                true => lines.push("VPN: Off".into()),
+                false => lines.push(format!("VPN: {}", connected.join(", "))),
+            }
+        }
+
+        if let Some(connectivity_state) = &self.connectivity_state {
+            lines.push(format!(
+                "Connectivity: {}",
+                connectivity_state.connectivity.label()
+            ));
+        }
+
+        ksni::ToolTip {
+            title: escape_markup(&title),
+            description: escape_markup(&lines.join("\n")),
+            ..Default::default()
+        }
+    }
+
     fn menu(&self) -> Vec<ksni::MenuItem<Self>> {
         use ksni::menu::{CheckmarkItem, RadioGroup, RadioItem, StandardItem, SubMenu};
 
         let mut menu = vec![];
 
+        let connectivity = self
+            .connectivity_state
+            .as_ref()
+            .map(|connectivity_state| connectivity_state.connectivity);
+
+        if connectivity == Some(Connectivity::Portal) {
+            menu.push(
+                StandardItem {
+                    label: "Sign in to Network…".into(),
+                    activate: Box::new(move |this: &mut Self| {
+                        this.app.send_action_blocking(Action::OpenCaptivePortal);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
+            menu.push(MenuItem::Separator);
+        }
+
         if let Some(wifi_state) = &self.wifi_state {
             let options = wifi_state
                 .known_connections
                 .iter()
                 .map(|connection| RadioItem {
-                    label: connection.ssid.clone(),
+                    label: match &wifi_state.activation {
+                        Some(activation) if activation.ssid == connection.ssid => {
+                            format!("{} ({})", connection.ssid, activation.stage.label())
+                        }
+                        _ => connection.ssid.clone(),
+                    },
                     ..Default::default()
                 })
                 .collect::<Vec<RadioItem>>();
@@ -204,8 +497,12 @@ impl ksni::Tray for NetworkTray {
 
             let mut submenu = vec![
                 CheckmarkItem {
-                    label: "On".into(),
+                    label: match wifi_state.hardware_blocked {
+                        true => "Disabled by hardware switch".into(),
+                        false => "On".into(),
+                    },
                     checked: wifi_state.on,
+                    enabled: !wifi_state.hardware_blocked,
                     activate: Box::new(move |this: &mut Self| {
                         this.app.send_action_blocking(Action::ToggleWifi);
                     }),
@@ -242,9 +539,12 @@ impl ksni::Tray for NetworkTray {
                 .into(),
             ];
 
-            let label = match wifi_state.known_connections.first() {
-                Some(connection) => format!("WiFi: {}", connection.ssid),
-                None => "WiFi".into(),
+            let label = match (&wifi_state.activation, wifi_state.known_connections.first()) {
+                (Some(activation), _) => {
+                    format!("WiFi: {} ({})", activation.ssid, activation.stage.label())
+                }
+                (None, Some(connection)) => format!("WiFi: {}", connection.ssid),
+                (None, None) => "WiFi".into(),
             };
 
             let mut available_connections = wifi_state
@@ -260,6 +560,18 @@ impl ksni::Tray for NetworkTray {
                 })
                 .collect::<Vec<MenuItem<Self>>>();
 
+            if let Some(failure) = &wifi_state.failure {
+                submenu.insert(
+                    1,
+                    StandardItem {
+                        label: format!("Failed: {}", failure),
+                        enabled: false,
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
+
             submenu.append(&mut available_connections);
 
             menu.push(
@@ -275,7 +587,10 @@ impl ksni::Tray for NetworkTray {
         if let Some(wired_state) = &self.wired_state {
             menu.push(
                 CheckmarkItem {
-                    label: "Wired".into(),
+                    label: match wired_state.stage {
+                        Some(stage) => format!("Wired ({})", stage.label()),
+                        None => "Wired".into(),
+                    },
                     checked: wired_state.on,
                     activate: Box::new(move |this: &mut Self| {
                         this.app.send_action_blocking(Action::ToggleWired);
@@ -284,6 +599,31 @@ impl ksni::Tray for NetworkTray {
                 }
                 .into(),
             );
+
+            if let Some(failure) = &wired_state.failure {
+                menu.push(
+                    StandardItem {
+                        label: format!("Failed: {}", failure),
+                        enabled: false,
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
+        }
+
+        if let Some(networking_state) = &self.networking_state {
+            menu.push(
+                CheckmarkItem {
+                    label: "Enable Networking".into(),
+                    checked: networking_state.on,
+                    activate: Box::new(move |this: &mut Self| {
+                        this.app.send_action_blocking(Action::ToggleNetworking);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
         }
 
         if let Some(airplane_mode_state) = &self.airplane_mode_state {
@@ -298,6 +638,34 @@ impl ksni::Tray for NetworkTray {
                 }
                 .into(),
             );
+
+            if !airplane_mode_state.radios.is_empty() {
+                let radios = airplane_mode_state
+                    .radios
+                    .iter()
+                    .map(|radio| {
+                        let action = radio.kind.toggle_action();
+                        CheckmarkItem {
+                            label: radio.label(),
+                            checked: radio.on,
+                            enabled: !radio.hardware_blocked,
+                            activate: Box::new(move |this: &mut Self| {
+                                this.app.send_action_blocking(action.clone());
+                            }),
+                            ..Default::default()
+                        }
+                        .into()
+                    })
+                    .collect::<Vec<MenuItem<Self>>>();
+                menu.push(
+                    SubMenu {
+                        label: "Radios".into(),
+                        submenu: radios,
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+            }
         }
 
         if let Some(vpn_state) = &self.vpn_state {
@@ -307,7 +675,10 @@ impl ksni::Tray for NetworkTray {
                 .map(|connection| {
                     let vpn_name = connection.name.clone();
                     CheckmarkItem {
-                        label: connection.name.clone(),
+                        label: match connection.stage {
+                            Some(stage) => format!("{} ({})", connection.name, stage.label()),
+                            None => connection.name.clone(),
+                        },
                         checked: connection.on,
                         activate: Box::new(move |this: &mut Self| {
                             this.app
@@ -328,6 +699,67 @@ impl ksni::Tray for NetworkTray {
             );
         }
 
+        if let Some(connectivity_state) = &self.connectivity_state {
+            let mut submenu = vec![];
+
+            for (version, connectivity) in [
+                ("IPv4", connectivity_state.ip4),
+                ("IPv6", connectivity_state.ip6),
+            ] {
+                if let Some(connectivity) = connectivity {
+                    submenu.push(
+                        StandardItem {
+                            label: format!("{}: {}", version, connectivity.label()),
+                            enabled: false,
+                            ..Default::default()
+                        }
+                        .into(),
+                    );
+                }
+            }
+
+            if !submenu.is_empty() {
+                submenu.push(MenuItem::Separator);
+            }
+
+            submenu.push(
+                StandardItem {
+                    label: "Check Now".into(),
+                    activate: Box::new(move |this: &mut Self| {
+                        this.app.send_action_blocking(Action::CheckConnectivity);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
+
+            submenu.push(
+                CheckmarkItem {
+                    label: match connectivity_state.check_available {
+                        true => "Check Automatically".into(),
+                        false => "Check Automatically (Not Configured)".into(),
+                    },
+                    checked: connectivity_state.check_enabled,
+                    enabled: connectivity_state.check_available,
+                    activate: Box::new(move |this: &mut Self| {
+                        this.app
+                            .send_action_blocking(Action::ToggleConnectivityCheck);
+                    }),
+                    ..Default::default()
+                }
+                .into(),
+            );
+
+            menu.push(
+                SubMenu {
+                    label: format!("Connectivity: {}", connectivity_state.connectivity.label()),
+                    submenu,
+                    ..Default::default()
+                }
+                .into(),
+            );
+        }
+
         menu
     }
 }
@@ -0,0 +1,142 @@
+use std::io::{self, Write};
+
+use clap::ValueEnum;
+use ksni::Tray;
+use log::error;
+use serde::Serialize;
+
+use crate::app::{App, Event};
+
+use super::{Base, Icon, TrayManager, TrayUpdate, network_tray::NetworkTray};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// One JSON object per line, as read by waybar's custom modules.
+    Json,
+    /// Only the text, for polybar, i3blocks and the like.
+    Text,
+}
+
+/// A line in waybar's custom module protocol.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Line {
+    text: String,
+    /// The icon's override name, which waybar matches against `format-icons`.
+    alt: String,
+    tooltip: String,
+    class: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    percentage: Option<u8>,
+}
+
+/// Writes the state to stdout for status bars without a StatusNotifierHost, at most one line per
+/// update pass.
+/// Clicks are expected to go through `networkless-rs ctl`.
+pub struct StatusBar {
+    app: App,
+    format: OutputFormat,
+    // Never spawned; only kept to work out the title, icon and tooltip the same way the tray does.
+    network_tray: NetworkTray,
+    last_line: Option<String>,
+}
+
+impl StatusBar {
+    pub fn new(app: App, format: OutputFormat) -> Self {
+        Self {
+            network_tray: NetworkTray::new(app.clone()),
+            app,
+            format,
+            last_line: None,
+        }
+    }
+
+    fn line(&self) -> Line {
+        let tray = &self.network_tray;
+
+        let text = match &tray.primary_connection_state {
+            Some(primary_connection_state) => primary_connection_state.id.clone(),
+            None => tray.title(),
+        };
+
+        let tool_tip = tray.tool_tip();
+        let tooltip = match tool_tip.description.is_empty() {
+            true => tool_tip.title,
+            false => format!("{}\n{}", tool_tip.title, tool_tip.description),
+        };
+
+        let percentage = match tray.icon() {
+            Some(Icon::Connected {
+                base: Base::Wifi(strength),
+                ..
+            }) => Some(*strength),
+            _ => None,
+        };
+
+        Line {
+            text,
+            alt: tray.icon_key().unwrap_or_default().into(),
+            tooltip,
+            class: tray.icon().map(Icon::class).unwrap_or_default().into(),
+            percentage,
+        }
+    }
+
+    fn print(&mut self) {
+        let line = self.line();
+
+        let line = match self.format {
+            OutputFormat::Json => match serde_json::to_string(&line) {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("Failed to serialize status: {}", e);
+                    return;
+                }
+            },
+            OutputFormat::Text => line.text,
+        };
+
+        if self.last_line.as_ref() == Some(&line) {
+            return;
+        }
+
+        let mut stdout = io::stdout().lock();
+        if let Err(e) = writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+            // The bar has gone away, so there's nobody left to show anything to.
+            error!("Failed to write status: {}", e);
+
+            // Sent from a task of its own, as this runs inside the loop that reads the events.
+            let app = self.app.clone();
+            tokio::spawn(async move {
+                app.send_event(Event::Shutdown).await;
+            });
+            return;
+        }
+
+        self.last_line = Some(line);
+    }
+}
+
+impl TrayManager for StatusBar {
+    async fn update(&mut self, update: TrayUpdate) {
+        let tray = &mut self.network_tray;
+
+        match update {
+            TrayUpdate::Icon(icon) => tray.set_icon(icon),
+            TrayUpdate::Wireless(state) => tray.set_wifi_state(state),
+            TrayUpdate::Wired(state) => tray.set_wired_state(state),
+            TrayUpdate::Vpn(state) => tray.set_vpn_state(state),
+            TrayUpdate::AirplaneMode(state) => tray.set_airplane_mode_state(state),
+            TrayUpdate::Networking(state) => tray.set_networking_state(state),
+            TrayUpdate::PrimaryConnection(state) => tray.set_primary_connection_state(state),
+            TrayUpdate::Connectivity(state) => tray.set_connectivity_state(state),
+        }
+    }
+
+    async fn refresh(&mut self) {
+        self.print();
+    }
+
+    async fn flush(&mut self) {
+        self.print();
+    }
+}
@@ -0,0 +1,97 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use futures::{StreamExt, future, stream};
+use log::{info, warn};
+use zbus::{Connection, fdo::DBusProxy, proxy::CacheProperties};
+
+use crate::interfaces::status_notifier_watcher::StatusNotifierWatcherProxy;
+
+const STATUS_NOTIFIER_WATCHER: &str = "org.kde.StatusNotifierWatcher";
+
+/// Calls `f` every time a StatusNotifierHost becomes available: once at startup if one is
+/// already registered, whenever a host registers, and whenever the watcher itself comes back.
+/// Waits for the session bus for as long as it takes.
+pub async fn listening_to_status_notifier_hosts<F>(f: F) -> Result<()>
+where
+    F: AsyncFnOnce() -> () + Send + Copy,
+{
+    let connection = wait_for_session_bus().await;
+
+    let dbus = DBusProxy::new(&connection).await?;
+    // Watchers don't reliably emit PropertiesChanged, so always ask for the current value.
+    let watcher = StatusNotifierWatcherProxy::builder(&connection)
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?;
+
+    let watcher_appeared = dbus
+        .receive_name_owner_changed_with_args(&[(0, STATUS_NOTIFIER_WATCHER)])
+        .await?
+        .filter_map(|name_owner_changed| {
+            let appeared = match name_owner_changed.args() {
+                Ok(args) => args.new_owner().is_some(),
+                Err(_) => false,
+            };
+            future::ready(appeared.then_some(()))
+        });
+
+    let host_registered = watcher
+        .receive_status_notifier_host_registered()
+        .await?
+        .map(|_| ());
+
+    let mut stream = stream::select(watcher_appeared, host_registered);
+
+    if is_host_registered(&watcher).await {
+        info!("StatusNotifierHost is available");
+        f().await;
+    }
+
+    while stream.next().await.is_some() {
+        if is_host_registered(&watcher).await {
+            info!("StatusNotifierHost registered");
+            f().await;
+        }
+    }
+
+    Ok(())
+}
+
+async fn is_host_registered(watcher: &StatusNotifierWatcherProxy<'_>) -> bool {
+    match watcher.is_status_notifier_host_registered().await {
+        Ok(registered) => registered,
+        Err(e) => {
+            warn!("Failed to get IsStatusNotifierHostRegistered: {}", e);
+            false
+        }
+    }
+}
+
+async fn wait_for_session_bus() -> Connection {
+    let mut attempt = 0u32;
+
+    loop {
+        attempt = attempt.saturating_add(1);
+
+        match Connection::session().await {
+            Ok(connection) => {
+                info!("Connected to session bus (attempt {})", attempt);
+                return connection;
+            }
+            Err(e) => {
+                warn!(
+                    "Failed to connect to session bus: {} (attempt {})",
+                    e, attempt
+                );
+            }
+        }
+
+        tokio::time::sleep(Duration::from_millis(
+            100_u64
+                .saturating_mul(2_u64.saturating_pow(attempt))
+                .min(5_000),
+        ))
+        .await;
+    }
+}
@@ -1,14 +1,13 @@
-use std::sync::LazyLock;
+use std::time::Duration;
 
-use ksni::{Icon, MenuItem, Tray, menu::CheckmarkItem};
+use ksni::{Icon, MenuItem, ToolTip, Tray, menu::CheckmarkItem};
 
 use crate::{
     APP_ID,
     app::{Action, App},
-    trays::get_icon_from_image_bytes,
 };
 
-use super::VPNState;
+use super::{VPNState, escape_markup, icons};
 
 pub struct VpnTray {
     app: App,
@@ -23,6 +22,12 @@ impl VpnTray {
     pub fn set_state(&mut self, state: Option<VPNState>) {
         self.state = state;
     }
+
+    fn is_connected(&self) -> bool {
+        self.state
+            .as_ref()
+            .is_some_and(|state| state.connections.iter().any(|c| c.on))
+    }
 }
 
 impl Tray for VpnTray {
@@ -45,21 +50,47 @@ impl Tray for VpnTray {
         "VPN".into()
     }
 
+    fn icon_name(&self) -> String {
+        if self.is_connected() {
+            return icons::VPN.icon_name(&self.app.config().icons);
+        }
+
+        String::new()
+    }
+
     fn icon_pixmap(&self) -> Vec<Icon> {
-        let mut icon = Vec::with_capacity(1);
+        if self.is_connected() {
+            return icons::VPN.icon_pixmap();
+        }
 
-        static VPN_ICON: LazyLock<Icon> = LazyLock::new(|| {
-            get_icon_from_image_bytes(include_bytes!("../../assets/virtual-vpn.png"))
-        });
+        vec![]
+    }
+
+    fn tool_tip(&self) -> ToolTip {
+        let mut lines = vec![];
 
         if let Some(state) = &self.state {
-            if state.connections.iter().any(|c| c.on) {
-                icon.push(VPN_ICON.clone());
-                return icon;
+            for connection in state.connections.iter().filter(|c| c.on) {
+                lines.push(connection.name.clone());
+
+                if let Some(endpoint) = &connection.endpoint {
+                    lines.push(format!("Endpoint: {}", endpoint));
+                }
+
+                if let Some(uptime) = connection
+                    .connected_since
+                    .and_then(|since| since.elapsed().ok())
+                {
+                    lines.push(format!("Connected for {}", format_uptime(uptime)));
+                }
             }
         }
 
-        icon
+        ToolTip {
+            title: escape_markup(&self.title()),
+            description: escape_markup(&lines.join("\n")),
+            ..Default::default()
+        }
     }
 
     fn menu(&self) -> Vec<MenuItem<Self>> {
@@ -72,7 +103,10 @@ impl Tray for VpnTray {
                 .map(|connection| {
                     let vpn_name = connection.name.clone();
                     CheckmarkItem {
-                        label: connection.name.clone(),
+                        label: match connection.stage {
+                            Some(stage) => format!("{} ({})", connection.name, stage.label()),
+                            None => connection.name.clone(),
+                        },
                         checked: connection.on,
                         activate: Box::new(move |this: &mut Self| {
                             this.app
@@ -91,3 +125,13 @@ impl Tray for VpnTray {
         menu
     }
 }
+
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+
+    match (minutes / 60, minutes % 60) {
+        (0, 0) => "less than a minute".into(),
+        (0, minutes) => format!("{} min", minutes),
+        (hours, minutes) => format!("{} h {} min", hours, minutes),
+    }
+}